@@ -0,0 +1,50 @@
+use eframe::egui::{self, Color32};
+use pomodoro_todo_list::error::Result;
+
+/// Non-fatal errors shown at the top of the window until dismissed, so a
+/// failed write or a missing asset doesn't end the session.
+#[derive(Default)]
+pub struct ErrorBanner {
+    messages: Vec<String>,
+}
+
+impl ErrorBanner {
+    /// Unwraps `result`, recording the error as "`action`: error" if it failed.
+    pub fn report<T>(&mut self, action: &str, result: Result<T>) -> Option<T> {
+        match result {
+            Ok(value) => Some(value),
+            Err(err) => {
+                let message = format!("{}: {}", action, err);
+                // Reads run every frame, don't stack up the same failure.
+                if !self.messages.contains(&message) {
+                    self.messages.push(message);
+                }
+                None
+            }
+        }
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context) {
+        if self.messages.is_empty() {
+            return;
+        }
+        egui::TopBottomPanel::top("error_banner").show(ctx, |ui| {
+            let mut dismissed = None;
+            for (index, message) in self.messages.iter().enumerate() {
+                ui.horizontal(|ui| {
+                    ui.colored_label(Color32::from_hex("#A80000").unwrap(), message);
+                    if ui
+                        .add(egui::Button::frame(egui::Button::new("x"), false))
+                        .on_hover_cursor(egui::CursorIcon::PointingHand)
+                        .clicked()
+                    {
+                        dismissed = Some(index);
+                    }
+                });
+            }
+            if let Some(index) = dismissed {
+                self.messages.remove(index);
+            }
+        });
+    }
+}
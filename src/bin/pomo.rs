@@ -0,0 +1,520 @@
+//! Headless companion to the GUI, working on the same `tasks.db`.
+use pomodoro_todo_list::config::{Configuration, CONFIGURATION_PATH};
+use pomodoro_todo_list::export::{export, export_to_file, Format};
+use pomodoro_todo_list::import::{import, parse_file};
+use pomodoro_todo_list::model::{
+    interruption_marks, now, parse_date, split_tags, task_tree, Interruption, InterruptionKind,
+    Task, Timer, TimerKind, DEFAULT_PRIORITY,
+};
+use pomodoro_todo_list::recurrence::Recurrence;
+use pomodoro_todo_list::store::{SqliteStore, Store, DATABASE_PATH};
+use std::collections::HashSet;
+use std::error::Error;
+use std::process::ExitCode;
+use time::macros::format_description;
+use time::Date;
+
+type Result<T> = std::result::Result<T, Box<dyn Error>>;
+
+const USAGE: &str = "usage: pomo <command> [args]
+
+commands:
+  add <name> [--estimate <n>] [--project <project>] [--parent <id>]
+      [--due <day>] [--plan <day>] [--repeat <rule>] [--priority <1-4>]
+                                add a task, #words in the name are tags
+  list                          list open tasks, subtasks under their parent
+  today                         list tasks planned or due today, and overdue
+  done <id>                     mark a task as done
+  history [--from <date>] [--to <date>]
+                                list done tasks, dates as YYYY-MM-DD
+  reopen <id>                   move a done task back to the list
+  estimate <id> <n>             set a task's estimate
+  due <id> <day>                set the day a task is due
+  plan <id> <day>               set the day to work on a task
+  repeat <id> <rule>            make a task come back once it is done
+  tag <id> [<tag>...]           set a task's tags, none to clear them
+  priority <id> <1-4>           set a task's priority, 1 is the most urgent
+  note <id> [<text>]            show a task's notes, or replace them with text
+  move <id> <project>           move a task to a project, or to the inbox
+  projects                      list projects with their pomodoros
+  project <name>                create a project
+  start focus [<id>]|short|long start a timer, a focus timer for a task is
+                                credited to it when stopped once over
+  stop [<reason>]               stop the running timer, which is kept as
+                                voided, or once over as a completed break or
+                                an abandoned pomodoro
+  status                        show the running timer
+  interrupt internal|external [<note>]
+                                log an interruption of the running focus timer
+  export json|csv|md [--output <file>]
+                                export tasks and timers, to stdout by default
+  import <file> [--dry-run]     add tasks from a .txt, .md or .csv file,
+                                --dry-run only lists them
+
+Days are YYYY-MM-DD, today, tomorrow or none. Rules are daily, weekdays,
+weekly:mon,thu, every:3 (days) or none.
+
+pomo uses tasks.db and configuration.json in the working directory.";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let Some((command, args)) = args.split_first() else {
+        eprintln!("{}", USAGE);
+        return ExitCode::FAILURE;
+    };
+    let result = match SqliteStore::open(DATABASE_PATH) {
+        Ok(mut store) => run(&mut store, command, args),
+        Err(err) => Err(err.into()),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(message) => {
+            eprintln!("pomo: {}", message);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn parse_number(value: Option<&String>, what: &str) -> Result<i32> {
+    let value = value.ok_or_else(|| format!("missing {}\n\n{}", what, USAGE))?;
+    Ok(value
+        .parse()
+        .map_err(|_| format!("{} must be a number, got '{}'", what, value))?)
+}
+
+/// A day as typed on the command line, `None` for `none`.
+fn parse_day(value: Option<&String>, what: &str) -> Result<Option<Date>> {
+    let value = value.ok_or_else(|| format!("missing {}\n\n{}", what, USAGE))?;
+    let today = now().date();
+    match value.as_str() {
+        "none" => Ok(None),
+        "today" => Ok(Some(today)),
+        "tomorrow" => Ok(today.next_day()),
+        _ => Ok(Some(parse_date(value).ok_or_else(|| {
+            format!("{} looks like 2024-05-31, got '{}'", what, value)
+        })?)),
+    }
+}
+
+/// A recurrence rule as typed on the command line, `None` for `none`.
+fn parse_rule(value: Option<&String>) -> Result<Option<Recurrence>> {
+    let value = value.ok_or_else(|| format!("missing rule\n\n{}", USAGE))?;
+    if value == "none" {
+        return Ok(None);
+    }
+    Ok(Some(Recurrence::parse(value).ok_or_else(|| {
+        format!("unknown rule '{}'\n\n{}", value, USAGE)
+    })?))
+}
+
+fn parse_priority(value: Option<&String>) -> Result<u8> {
+    match parse_number(value, "priority")? {
+        priority @ 1..=4 => Ok(priority as u8),
+        priority => Err(format!("priority must be 1 to 4, got {}", priority).into()),
+    }
+}
+
+fn run(store: &mut SqliteStore, command: &str, args: &[String]) -> Result<()> {
+    match command {
+        "add" => {
+            let mut name: Vec<&str> = vec![];
+            let mut estimate = 0;
+            let mut project = None;
+            let mut parent = None;
+            let mut due = None;
+            let mut planned = None;
+            let mut recurrence = None;
+            let mut priority = DEFAULT_PRIORITY;
+            let mut args = args.iter();
+            while let Some(arg) = args.next() {
+                if arg == "--estimate" {
+                    estimate = parse_number(args.next(), "estimate")?;
+                } else if arg == "--project" {
+                    project = find_project(store, args.next())?;
+                } else if arg == "--parent" {
+                    let id = parse_number(args.next(), "parent id")?;
+                    project = project.or(find_task(store, id)?.project);
+                    parent = Some(id);
+                } else if arg == "--due" {
+                    due = parse_day(args.next(), "due day")?;
+                } else if arg == "--plan" {
+                    planned = parse_day(args.next(), "planned day")?;
+                } else if arg == "--repeat" {
+                    recurrence = parse_rule(args.next())?;
+                } else if arg == "--priority" {
+                    priority = parse_priority(args.next())?;
+                } else {
+                    name.push(arg);
+                }
+            }
+            let (name, tags) = split_tags(&name.join(" "));
+            if name.is_empty() {
+                return Err(format!("missing task name\n\n{}", USAGE).into());
+            }
+            store.create_task(Task {
+                estimate,
+                project,
+                parent,
+                due,
+                planned,
+                recurrence,
+                tags,
+                priority,
+                ..Task::new(&name)
+            })?;
+            Ok(())
+        }
+        "list" => list(store),
+        "today" => today(store),
+        "move" => {
+            let id = parse_number(args.first(), "task id")?;
+            let project = find_project(store, args.get(1))?;
+            find_task(store, id)?;
+            Ok(store.set_task_project(project, id)?)
+        }
+        "projects" => projects(store),
+        "project" => {
+            if args.is_empty() {
+                return Err(format!("missing project name\n\n{}", USAGE).into());
+            }
+            Ok(store.create_project(args.join(" "))?)
+        }
+        "done" => {
+            let id = parse_number(args.first(), "task id")?;
+            find_task(store, id)?;
+            Ok(store.set_task_status(true, id)?)
+        }
+        "history" => {
+            let mut from = None;
+            let mut to = None;
+            let mut args = args.iter();
+            while let Some(arg) = args.next() {
+                let date = match arg.as_str() {
+                    "--from" => &mut from,
+                    "--to" => &mut to,
+                    _ => return Err(format!("unexpected argument '{}'\n\n{}", arg, USAGE).into()),
+                };
+                let value = args
+                    .next()
+                    .ok_or_else(|| format!("missing date after {}\n\n{}", arg, USAGE))?;
+                *date = Some(
+                    parse_date(value)
+                        .ok_or_else(|| format!("dates look like 2024-05-31, got '{}'", value))?,
+                );
+            }
+            history(store, from, to)
+        }
+        "reopen" => {
+            let id = parse_number(args.first(), "task id")?;
+            if !store
+                .get_done_tasks(None, None)?
+                .iter()
+                .any(|task| task.id == id)
+            {
+                return Err(format!("no done task with id {}", id).into());
+            }
+            Ok(store.set_task_status(false, id)?)
+        }
+        "estimate" => {
+            let id = parse_number(args.first(), "task id")?;
+            let estimate = parse_number(args.get(1), "estimate")?;
+            find_task(store, id)?;
+            Ok(store.set_task_estimate(estimate, id)?)
+        }
+        "due" => {
+            let id = parse_number(args.first(), "task id")?;
+            let due = parse_day(args.get(1), "due day")?;
+            find_task(store, id)?;
+            Ok(store.set_task_due(due, id)?)
+        }
+        "plan" => {
+            let id = parse_number(args.first(), "task id")?;
+            let planned = parse_day(args.get(1), "planned day")?;
+            find_task(store, id)?;
+            Ok(store.set_task_planned(planned, id)?)
+        }
+        "repeat" => {
+            let id = parse_number(args.first(), "task id")?;
+            let recurrence = parse_rule(args.get(1))?;
+            find_task(store, id)?;
+            Ok(store.set_task_recurrence(recurrence, id)?)
+        }
+        "priority" => {
+            let id = parse_number(args.first(), "task id")?;
+            let priority = parse_priority(args.get(1))?;
+            find_task(store, id)?;
+            Ok(store.set_task_priority(priority, id)?)
+        }
+        "note" => {
+            let id = parse_number(args.first(), "task id")?;
+            let task = find_task(store, id)?;
+            if args.len() < 2 {
+                println!("{}", task.notes);
+                return Ok(());
+            }
+            Ok(store.set_task_notes(args[1..].join(" "), id)?)
+        }
+        "tag" => {
+            let id = parse_number(args.first(), "task id")?;
+            let words: Vec<&str> = args[1..]
+                .iter()
+                .map(|tag| tag.trim_start_matches('#'))
+                .collect();
+            let (_, tags) = split_tags(&format!("#{}", words.join(" #")));
+            find_task(store, id)?;
+            Ok(store.set_task_tags(tags, id)?)
+        }
+        "start" => {
+            let kind = match args.first().map(String::as_str) {
+                Some("focus") => TimerKind::Focus,
+                Some("short") => TimerKind::ShortBreak,
+                Some("long") => TimerKind::LongBreak,
+                _ => return Err(format!("start needs focus, short or long\n\n{}", USAGE).into()),
+            };
+            let running = store.get_running_timers()?;
+            if !running.is_empty() {
+                return Err("a timer is already running, stop it first".into());
+            }
+            let configuration = Configuration::load(CONFIGURATION_PATH)?;
+            let mut timer = Timer::new(kind, configuration.duration(kind), now());
+            if kind == TimerKind::Focus && args.len() > 1 {
+                let id = parse_number(args.get(1), "task id")?;
+                find_task(store, id)?;
+                timer.task = Some(id);
+            }
+            Ok(store.create_timer(timer)?)
+        }
+        "stop" => {
+            let reason = args.join(" ");
+            for timer in store.get_running_timers()? {
+                let status = timer.stopped_status(now());
+                store.set_timer_status(timer.id, status, reason.clone())?;
+                let kind = if timer.is_pomodoro() {
+                    "Pomodoro"
+                } else {
+                    "Break"
+                };
+                println!("{} {}", kind, status.name());
+            }
+            Ok(())
+        }
+        "status" => status(store),
+        "interrupt" => {
+            let kind = match args.first().map(String::as_str) {
+                Some("internal") => InterruptionKind::Internal,
+                Some("external") => InterruptionKind::External,
+                _ => {
+                    return Err(format!("interrupt needs internal or external\n\n{}", USAGE).into())
+                }
+            };
+            let timer = store
+                .get_running_timers()?
+                .into_iter()
+                .find(|timer| timer.is_pomodoro())
+                .ok_or("no focus timer running")?;
+            Ok(store.create_interruption(Interruption::new(
+                timer.id,
+                kind,
+                now(),
+                args[1..].join(" "),
+            ))?)
+        }
+        "export" => {
+            let format = args
+                .first()
+                .and_then(|name| Format::from_name(name))
+                .ok_or_else(|| format!("export needs json, csv or md\n\n{}", USAGE))?;
+            match args.get(1).map(String::as_str) {
+                None => {
+                    print!("{}", export(store, format)?);
+                    Ok(())
+                }
+                Some("--output") => {
+                    let path = args
+                        .get(2)
+                        .ok_or_else(|| format!("missing output file\n\n{}", USAGE))?;
+                    Ok(export_to_file(store, format, path)?)
+                }
+                Some(arg) => Err(format!("unexpected argument '{}'\n\n{}", arg, USAGE).into()),
+            }
+        }
+        "import" => {
+            let path = args
+                .first()
+                .ok_or_else(|| format!("missing file to import\n\n{}", USAGE))?;
+            let dry_run = match args.get(1).map(String::as_str) {
+                None => false,
+                Some("--dry-run") => true,
+                Some(arg) => {
+                    return Err(format!("unexpected argument '{}'\n\n{}", arg, USAGE).into())
+                }
+            };
+            let parsed = parse_file(path)?;
+            let tasks = &parsed.tasks;
+            if dry_run {
+                for task in tasks.iter() {
+                    let check = if task.done { "x" } else { " " };
+                    println!("[{}] {}  {}", check, task.estimate, task.name);
+                }
+                println!("{} tasks would be imported", tasks.len());
+                return Ok(());
+            }
+            import(store, &parsed)?;
+            println!("Imported {} tasks", tasks.len());
+            Ok(())
+        }
+        _ => Err(format!("unknown command '{}'\n\n{}", command, USAGE).into()),
+    }
+}
+
+fn find_task(store: &SqliteStore, id: i32) -> Result<Task> {
+    let tasks = store.get_tasks()?;
+    Ok(tasks
+        .into_iter()
+        .find(|task| task.id == id)
+        .ok_or_else(|| format!("no open task with id {}", id))?)
+}
+
+/// Looks a project up by name; `inbox` stands for no project.
+fn find_project(store: &SqliteStore, name: Option<&String>) -> Result<Option<i32>> {
+    let name = name.ok_or_else(|| format!("missing project name\n\n{}", USAGE))?;
+    if name == "inbox" {
+        return Ok(None);
+    }
+    let projects = store.get_projects()?;
+    Ok(Some(
+        projects
+            .into_iter()
+            .find(|project| project.name == *name)
+            .ok_or_else(|| format!("no project named '{}'", name))?
+            .id,
+    ))
+}
+
+fn projects(store: &SqliteStore) -> Result<()> {
+    println!("{:>4}  inbox", store.get_project_pomodoros(None)?);
+    for project in store.get_projects()? {
+        let pomodoros = store.get_project_pomodoros(Some(project.id))?;
+        println!("{:>4}  {}", pomodoros, project.name);
+    }
+    Ok(())
+}
+
+/// `P1 ` to `P3 ` before a task's name, nothing for the default P4.
+fn priority_label(task: &Task) -> String {
+    if task.priority < DEFAULT_PRIORITY {
+        format!("P{} ", task.priority)
+    } else {
+        "".to_string()
+    }
+}
+
+/// `due <day>` after a task's name, `overdue <day>` once it has passed,
+/// followed by how the task repeats and its tags.
+fn due_label(task: &Task, today: Date) -> String {
+    let mut label = match task.due {
+        Some(due) if task.is_overdue(today) => format!("  overdue {}", due),
+        Some(due) => format!("  due {}", due),
+        None => "".to_string(),
+    };
+    if let Some(recurrence) = &task.recurrence {
+        label += &format!("  repeats {}", recurrence.label());
+    }
+    for tag in task.tags.iter() {
+        label += &format!(" #{}", tag);
+    }
+    label
+}
+
+fn list(store: &SqliteStore) -> Result<()> {
+    let tasks = store.get_tasks()?;
+    let today = now().date();
+    for (index, depth) in task_tree(&tasks, &HashSet::new()) {
+        let task = &tasks[index];
+        let pomodoros = store.get_task_pomodoros(task.id)?;
+        let estimate = store.get_task_estimate(task.id)?;
+        println!(
+            "{:>4}  {}/{}  {}{}{}{}",
+            task.id,
+            pomodoros,
+            estimate,
+            "  ".repeat(depth),
+            priority_label(task),
+            task.name,
+            due_label(task, today)
+        );
+    }
+    Ok(())
+}
+
+fn today(store: &SqliteStore) -> Result<()> {
+    let today = now().date();
+    for task in store.get_tasks()? {
+        if task.is_for_today(today) {
+            let pomodoros = store.get_task_pomodoros(task.id)?;
+            let estimate = store.get_task_estimate(task.id)?;
+            println!(
+                "{:>4}  {}/{}  {}{}{}",
+                task.id,
+                pomodoros,
+                estimate,
+                priority_label(&task),
+                task.name,
+                due_label(&task, today)
+            );
+        }
+    }
+    Ok(())
+}
+
+fn history(store: &SqliteStore, from: Option<Date>, to: Option<Date>) -> Result<()> {
+    for task in store.get_done_tasks(from, to)? {
+        let completed_at = task
+            .completed_at
+            .and_then(|completed_at| {
+                completed_at
+                    .format(format_description!("[year]-[month]-[day] [hour]:[minute]"))
+                    .ok()
+            })
+            .unwrap_or_else(|| "-".to_string());
+        let pomodoros = store.get_task_pomodoros(task.id)?;
+        println!(
+            "{:>4}  {:<16}  {}/{}  {}",
+            task.id, completed_at, pomodoros, task.estimate, task.name
+        );
+    }
+    Ok(())
+}
+
+fn status(store: &SqliteStore) -> Result<()> {
+    let timers = store.get_running_timers()?;
+    let Some(timer) = timers.first() else {
+        println!("No timer running");
+        return Ok(());
+    };
+    let remaining = timer.remaining(now()).whole_seconds();
+    let kind = timer.kind.label();
+    let status = if remaining <= 0 {
+        format!("{} done", kind)
+    } else if timer.is_paused() {
+        format!(
+            "{} paused, {:0>2}:{:0>2} left",
+            kind,
+            remaining / 60,
+            remaining % 60
+        )
+    } else {
+        format!("{} {:0>2}:{:0>2}", kind, remaining / 60, remaining % 60)
+    };
+    let status = match timer.task.and_then(|id| find_task(store, id).ok()) {
+        Some(task) => format!("{} on {}", status, task.name),
+        None => status,
+    };
+    let interruptions = store.get_timer_interruptions(timer.id)?;
+    if interruptions.is_empty() {
+        println!("{}", status);
+    } else {
+        println!("{} {}", status, interruption_marks(&interruptions));
+    }
+    Ok(())
+}
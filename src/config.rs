@@ -0,0 +1,57 @@
+use crate::error::{open_file, Result};
+use crate::model::TimerKind;
+use serde::Deserialize;
+use std::path::Path;
+
+pub const CONFIGURATION_PATH: &str = "./configuration.json";
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct Configuration {
+    pub focus_duration: i32,
+    pub short_break_duration: i32,
+    pub long_break_duration: i32,
+    /// Suggest (or start) the next timer of the focus/break cycle when one finishes.
+    #[serde(default)]
+    pub cycle_mode: bool,
+    /// Number of focus sessions before a long break replaces the short one.
+    #[serde(default = "default_long_break_interval")]
+    pub long_break_interval: i32,
+    #[serde(default)]
+    pub auto_start_breaks: bool,
+    #[serde(default)]
+    pub auto_start_focus: bool,
+}
+
+fn default_long_break_interval() -> i32 {
+    4
+}
+
+impl Default for Configuration {
+    fn default() -> Self {
+        Configuration {
+            focus_duration: 20,
+            short_break_duration: 10,
+            long_break_duration: 30,
+            cycle_mode: false,
+            long_break_interval: default_long_break_interval(),
+            auto_start_breaks: false,
+            auto_start_focus: false,
+        }
+    }
+}
+
+impl Configuration {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let file = open_file(path.as_ref())?;
+        Ok(serde_json::from_reader(file)?)
+    }
+
+    pub fn duration(&self, kind: TimerKind) -> i32 {
+        match kind {
+            TimerKind::Focus => self.focus_duration,
+            TimerKind::ShortBreak => self.short_break_duration,
+            TimerKind::LongBreak => self.long_break_duration,
+        }
+    }
+}
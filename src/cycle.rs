@@ -0,0 +1,63 @@
+use crate::model::TimerKind;
+
+/// Position in the focus/break loop: short breaks between focus sessions,
+/// and a long break once `long_break_interval` sessions are done.
+#[derive(Default)]
+pub struct Cycle {
+    /// Focus sessions finished since the last long break.
+    pub completed: i32,
+}
+
+impl Cycle {
+    /// Records that a timer of `kind` finished and returns what should run next.
+    pub fn advance(&mut self, finished: TimerKind, long_break_interval: i32) -> TimerKind {
+        match finished {
+            TimerKind::Focus => {
+                self.completed += 1;
+                if self.completed >= long_break_interval.max(1) {
+                    TimerKind::LongBreak
+                } else {
+                    TimerKind::ShortBreak
+                }
+            }
+            TimerKind::ShortBreak => TimerKind::Focus,
+            TimerKind::LongBreak => {
+                self.completed = 0;
+                TimerKind::Focus
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn long_break_every_nth_focus() {
+        let mut cycle = Cycle::default();
+        let mut breaks = vec![];
+        for _ in 0..4 {
+            let next = cycle.advance(TimerKind::Focus, 4);
+            breaks.push(next);
+            assert_eq!(cycle.advance(next, 4), TimerKind::Focus);
+        }
+        assert_eq!(
+            breaks,
+            vec![
+                TimerKind::ShortBreak,
+                TimerKind::ShortBreak,
+                TimerKind::ShortBreak,
+                TimerKind::LongBreak
+            ]
+        );
+        assert_eq!(cycle.completed, 0);
+    }
+
+    #[test]
+    fn skipped_long_break_is_suggested_again() {
+        let mut cycle = Cycle { completed: 2 };
+        assert_eq!(cycle.advance(TimerKind::Focus, 2), TimerKind::LongBreak);
+        assert_eq!(cycle.advance(TimerKind::Focus, 2), TimerKind::LongBreak);
+    }
+}
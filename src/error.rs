@@ -0,0 +1,96 @@
+use std::fmt;
+use std::path::PathBuf;
+
+#[derive(Debug)]
+pub enum Error {
+    Database(rusqlite::Error),
+    SchemaTooNew {
+        found: i32,
+        supported: i32,
+    },
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    Configuration(serde_json::Error),
+    Audio(String),
+    Import(String),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Database(err) => write!(f, "database error: {}", err),
+            Error::SchemaTooNew { found, supported } => write!(
+                f,
+                "database schema version {} is newer than the supported version {}",
+                found, supported
+            ),
+            Error::Io { path, source } => write!(f, "{}: {}", path.display(), source),
+            Error::Configuration(err) => write!(f, "invalid configuration: {}", err),
+            Error::Audio(message) => write!(f, "audio error: {}", message),
+            Error::Import(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Database(err) => Some(err),
+            Error::Io { source, .. } => Some(source),
+            Error::Configuration(err) => Some(err),
+            Error::SchemaTooNew { .. } | Error::Audio(_) | Error::Import(_) => None,
+        }
+    }
+}
+
+impl From<rusqlite::Error> for Error {
+    fn from(err: rusqlite::Error) -> Self {
+        Error::Database(err)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Self {
+        Error::Configuration(err)
+    }
+}
+
+impl From<rodio::StreamError> for Error {
+    fn from(err: rodio::StreamError) -> Self {
+        Error::Audio(err.to_string())
+    }
+}
+
+impl From<rodio::decoder::DecoderError> for Error {
+    fn from(err: rodio::decoder::DecoderError) -> Self {
+        Error::Audio(err.to_string())
+    }
+}
+
+impl From<rodio::PlayError> for Error {
+    fn from(err: rodio::PlayError) -> Self {
+        Error::Audio(err.to_string())
+    }
+}
+
+/// Opens `path`, keeping the path around for the error message.
+pub fn open_file<P: Into<PathBuf>>(path: P) -> Result<std::fs::File> {
+    let path = path.into();
+    std::fs::File::open(&path).map_err(|source| Error::Io { path, source })
+}
+
+/// Reads `path` into a string, keeping the path around for the error message.
+pub fn read_file<P: Into<PathBuf>>(path: P) -> Result<String> {
+    let path = path.into();
+    std::fs::read_to_string(&path).map_err(|source| Error::Io { path, source })
+}
+
+/// Writes `contents` to `path`, keeping the path around for the error message.
+pub fn write_file<P: Into<PathBuf>, C: AsRef<[u8]>>(path: P, contents: C) -> Result<()> {
+    let path = path.into();
+    std::fs::write(&path, contents).map_err(|source| Error::Io { path, source })
+}
@@ -0,0 +1,368 @@
+//! Tasks and timers out of the store, for other tools.
+//!
+//! The layouts below are stable; new fields are only ever appended.
+//!
+//! JSON is an object with two arrays:
+//!
+//! ```text
+//! {
+//!   "tasks":  [{"id": 1, "name": "Write report", "done": false, "estimate": 3, "pomodoros": 2,
+//!               "project": "Work", "parent": null, "completed_at": null,
+//!               "due": "2024-05-03", "planned": null, "recurrence": "weekly:fri",
+//!               "tags": ["writing"], "priority": 2, "notes": "Outline in `draft.md`"}],
+//!   "timers": [{"id": 1, "kind": "focus", "start": "2024-05-01T09:00:00+02:00", "duration": 25, "task": 1,
+//!               "status": "completed", "reason": ""}]
+//! }
+//! ```
+//!
+//! CSV has one header row and one row per record, tasks first then timers.
+//! The `record` column says which fields apply, the others are left empty:
+//!
+//! ```text
+//! record,id,name,done,estimate,pomodoros,kind,start,duration,task,project,parent,completed_at,due,planned,recurrence,tags,priority,notes,status,reason
+//! task,1,Write report,false,3,2,,,,,Work,,,2024-05-03,,weekly:fri,writing,2,Outline in `draft.md`,,
+//! timer,1,,,,,focus,2024-05-01T09:00:00+02:00,25,1,,,,,,,,,,completed,
+//! ```
+//!
+//! Markdown is a checklist of every task with its tags and pomodoro count,
+//! followed by the estimate when there is one:
+//!
+//! ```text
+//! - [ ] Write report #writing (2/3 pomodoros)
+//! ```
+//!
+//! `kind` is `focus` or `break`, `start` is RFC 3339 in the offset the timer
+//! was started in, `duration` is in minutes and `task` is the id of the task
+//! the timer was credited to. A running or voided focus timer may carry the
+//! task being worked on; `task` is empty/`null` when there is none.
+//! `status` is `running`, `completed`, `voided` (stopped early) or
+//! `abandoned` (over but never credited), with the `reason` given for the
+//! last two. `pomodoros` only counts completed ones.
+//! `project` is the name of the task's project, empty/`null` for the inbox.
+//! `parent` is the id of the task a subtask belongs to. `estimate` and
+//! `pomodoros` are the task's own, not rolled up from its subtasks.
+//! `completed_at` is RFC 3339 like `start`, empty/`null` for open tasks and
+//! for tasks done before completion times were recorded. `due` and `planned`
+//! are days as `YYYY-MM-DD`, empty/`null` when not set. `recurrence` is the
+//! rule of a repeating task, like `daily` or `weekly:mon,thu`. `tags` are
+//! without the leading `#`, separated by spaces in CSV. `priority` runs from
+//! 1 (most urgent) to 4. `notes` is Markdown, possibly over several lines.
+//! Markdown export leaves the notes out.
+use crate::error::{write_file, Result};
+use crate::model::{Project, Task, Timer, TimerStatus};
+use crate::store::Store;
+use serde::Serialize;
+use std::path::Path;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Json,
+    Csv,
+    Markdown,
+}
+
+impl Format {
+    pub const ALL: [Format; 3] = [Format::Json, Format::Csv, Format::Markdown];
+
+    /// Parses the names accepted on the command line.
+    pub fn from_name(name: &str) -> Option<Format> {
+        match name {
+            "json" => Some(Format::Json),
+            "csv" => Some(Format::Csv),
+            "md" | "markdown" => Some(Format::Markdown),
+            _ => None,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Format::Json => "JSON",
+            Format::Csv => "CSV",
+            Format::Markdown => "Markdown",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Format::Json => "json",
+            Format::Csv => "csv",
+            Format::Markdown => "md",
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct TaskRecord {
+    id: i32,
+    name: String,
+    done: bool,
+    estimate: i32,
+    pomodoros: usize,
+    project: Option<String>,
+    parent: Option<i32>,
+    completed_at: Option<String>,
+    due: Option<String>,
+    planned: Option<String>,
+    recurrence: Option<String>,
+    tags: Vec<String>,
+    priority: u8,
+    notes: String,
+}
+
+#[derive(Serialize)]
+struct TimerRecord {
+    id: i32,
+    kind: &'static str,
+    start: String,
+    duration: i32,
+    task: Option<i32>,
+    status: &'static str,
+    reason: String,
+}
+
+#[derive(Serialize)]
+struct Export {
+    tasks: Vec<TaskRecord>,
+    timers: Vec<TimerRecord>,
+}
+
+fn task_record(task: Task, timers: &[Timer], projects: &[Project]) -> TaskRecord {
+    TaskRecord {
+        project: projects
+            .iter()
+            .find(|project| Some(project.id) == task.project)
+            .map(|project| project.name.clone()),
+        pomodoros: timers
+            .iter()
+            .filter(|timer| timer.is_pomodoro() && timer.status == TimerStatus::Completed)
+            .filter(|timer| timer.task == Some(task.id))
+            .count(),
+        id: task.id,
+        parent: task.parent,
+        completed_at: task.completed_at.map(rfc3339),
+        due: task.due.map(|due| due.to_string()),
+        planned: task.planned.map(|planned| planned.to_string()),
+        recurrence: task
+            .recurrence
+            .as_ref()
+            .map(|recurrence| recurrence.to_string()),
+        tags: task.tags.clone(),
+        priority: task.priority,
+        notes: task.notes.clone(),
+        name: task.name,
+        done: task.done,
+        estimate: task.estimate,
+    }
+}
+
+fn rfc3339(time: OffsetDateTime) -> String {
+    // Formatting only fails for years outside 0..=9999.
+    time.format(&Rfc3339).unwrap_or_default()
+}
+
+fn timer_record(timer: &Timer) -> TimerRecord {
+    TimerRecord {
+        id: timer.id,
+        kind: if timer.is_pomodoro() {
+            "focus"
+        } else {
+            "break"
+        },
+        start: rfc3339(timer.start),
+        duration: timer.duration,
+        task: timer.task,
+        status: timer.status.name(),
+        reason: timer.reason.clone(),
+    }
+}
+
+fn load(store: &dyn Store) -> Result<Export> {
+    let timers = store.get_all_timers()?;
+    let projects = store.get_projects()?;
+    Ok(Export {
+        tasks: store
+            .get_all_tasks()?
+            .into_iter()
+            .map(|task| task_record(task, &timers, &projects))
+            .collect(),
+        timers: timers.iter().map(timer_record).collect(),
+    })
+}
+
+/// Quotes a CSV field when it contains a separator, quote or line break.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn to_csv(export: &Export) -> String {
+    let mut csv = String::from(
+        "record,id,name,done,estimate,pomodoros,kind,start,duration,task,project,parent,completed_at,due,planned,recurrence,tags,priority,notes,status,reason\n",
+    );
+    for task in export.tasks.iter() {
+        csv += &format!(
+            "task,{},{},{},{},{},,,,,{},{},{},{},{},{},{},{},{},,\n",
+            task.id,
+            csv_field(&task.name),
+            task.done,
+            task.estimate,
+            task.pomodoros,
+            csv_field(task.project.as_deref().unwrap_or_default()),
+            task.parent
+                .map(|parent| parent.to_string())
+                .unwrap_or_default(),
+            task.completed_at.as_deref().unwrap_or_default(),
+            task.due.as_deref().unwrap_or_default(),
+            task.planned.as_deref().unwrap_or_default(),
+            task.recurrence.as_deref().unwrap_or_default(),
+            csv_field(&task.tags.join(" ")),
+            task.priority,
+            csv_field(&task.notes)
+        );
+    }
+    for timer in export.timers.iter() {
+        csv += &format!(
+            "timer,{},,,,,{},{},{},{},,,,,,,,,,{},{}\n",
+            timer.id,
+            timer.kind,
+            timer.start,
+            timer.duration,
+            timer.task.map(|task| task.to_string()).unwrap_or_default(),
+            timer.status,
+            csv_field(&timer.reason)
+        );
+    }
+    csv
+}
+
+fn to_markdown(export: &Export) -> String {
+    let mut markdown = String::new();
+    for task in export.tasks.iter() {
+        let check = if task.done { "x" } else { " " };
+        let unit = if task.pomodoros == 1 && task.estimate == 0 {
+            "pomodoro"
+        } else {
+            "pomodoros"
+        };
+        let count = if task.estimate > 0 {
+            format!("{}/{}", task.pomodoros, task.estimate)
+        } else {
+            task.pomodoros.to_string()
+        };
+        // A name starting a new line would break out of the list item.
+        let mut name = task.name.replace('\n', " ");
+        for tag in task.tags.iter() {
+            name += &format!(" #{}", tag);
+        }
+        markdown += &format!("- [{}] {} ({} {})\n", check, name, count, unit);
+    }
+    markdown
+}
+
+/// Every task and timer in `store`, laid out as `format`.
+pub fn export(store: &dyn Store, format: Format) -> Result<String> {
+    let export = load(store)?;
+    Ok(match format {
+        Format::Json => serde_json::to_string_pretty(&export)? + "\n",
+        Format::Csv => to_csv(&export),
+        Format::Markdown => to_markdown(&export),
+    })
+}
+
+pub fn export_to_file<P: AsRef<Path>>(store: &dyn Store, format: Format, path: P) -> Result<()> {
+    write_file(path.as_ref(), export(store, format)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::InMemoryStore;
+    use time::macros::datetime;
+
+    fn store() -> InMemoryStore {
+        let mut store = InMemoryStore::new();
+        for (name, estimate) in [("Write report", 3), ("Call \"Bob\", later", 0)] {
+            store
+                .create_task(Task {
+                    estimate,
+                    ..Task::new(name)
+                })
+                .unwrap();
+        }
+        store.set_task_status(true, 2).unwrap();
+        store.create_project("Work".to_string()).unwrap();
+        store.set_task_project(Some(1), 1).unwrap();
+        store.set_task_tags(vec!["writing".to_string()], 1).unwrap();
+        store
+            .set_task_due(Some(datetime!(2024-05-03 0:00 UTC).date()), 1)
+            .unwrap();
+        store
+            .set_task_notes("- outline\n- draft".to_string(), 1)
+            .unwrap();
+        for start in [
+            datetime!(2024-05-01 09:00 +2),
+            datetime!(2024-05-01 10:00 +2),
+        ] {
+            store
+                .create_timer(Timer::new(crate::model::TimerKind::Focus, 25, start))
+                .unwrap();
+        }
+        store.update_timer_task(1, 1).unwrap();
+        store
+            .set_timer_status(2, TimerStatus::Voided, "phone, twice".to_string())
+            .unwrap();
+        store
+    }
+
+    #[test]
+    fn json_lists_tasks_and_timers() {
+        let json: serde_json::Value =
+            serde_json::from_str(&export(&store(), Format::Json).unwrap()).unwrap();
+        assert_eq!(json["tasks"][0]["pomodoros"], 1);
+        assert_eq!(json["tasks"][1]["done"], true);
+        assert_eq!(json["tasks"][0]["project"], "Work");
+        assert!(json["tasks"][1]["project"].is_null());
+        assert_eq!(json["tasks"][0]["due"], "2024-05-03");
+        assert!(json["tasks"][0]["planned"].is_null());
+        assert_eq!(json["tasks"][0]["tags"][0], "writing");
+        assert_eq!(json["tasks"][0]["notes"], "- outline\n- draft");
+        assert_eq!(json["timers"][0]["start"], "2024-05-01T09:00:00+02:00");
+        assert_eq!(json["timers"][0]["task"], 1);
+        assert_eq!(json["timers"][1]["status"], "voided");
+    }
+
+    #[test]
+    fn csv_quotes_names() {
+        let csv = export(&store(), Format::Csv).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(
+            lines[1..3],
+            [
+                "task,1,Write report,false,3,1,,,,,Work,,,2024-05-03,,,writing,4,\"- outline",
+                "- draft\",,"
+            ]
+        );
+        // Ticked off just now, so only the completion time's presence is checked.
+        assert!(lines[3].starts_with("task,2,\"Call \"\"Bob\"\", later\",true,0,0,,,,,,,2"));
+        assert_eq!(
+            lines[4..],
+            [
+                "timer,1,,,,,focus,2024-05-01T09:00:00+02:00,25,1,,,,,,,,,,completed,",
+                "timer,2,,,,,focus,2024-05-01T10:00:00+02:00,25,,,,,,,,,,,voided,\"phone, twice\""
+            ]
+        );
+    }
+
+    #[test]
+    fn markdown_is_a_checklist() {
+        assert_eq!(
+            export(&store(), Format::Markdown).unwrap(),
+            "- [ ] Write report #writing (1/3 pomodoros)\n- [x] Call \"Bob\", later (0 pomodoros)\n"
+        );
+    }
+}
@@ -0,0 +1,489 @@
+//! Tasks from other tools into the store.
+//!
+//! Three formats are read, picked by file extension:
+//!
+//! - todo.txt (`.txt`): one task per line. A leading `x ` marks it done. A
+//!   priority `(A)` to `(C)` that follows becomes P1 to P3, any other letter
+//!   P4, and the creation date is dropped. The completion date of done tasks
+//!   is kept as their completion time.
+//! - Markdown (`.md`): `- [ ]` and `- [x]` list items, `*` and `+` bullets
+//!   too. Every other line is ignored.
+//! - CSV (`.csv`): a header row naming the columns, of which `name` is
+//!   required and `done`, `estimate`, `completed_at` (RFC 3339 or
+//!   `YYYY-MM-DD`), `due`, `planned`, `recurrence`, `tags` (separated by
+//!   spaces), `priority` (`1` to `4`, or `P1` to `P4`), `notes`, `project`
+//!   (a name, created if missing), `id` and `parent` (the `id` of another
+//!   row) are optional. Files written by the CSV export can be read back;
+//!   only their `task` rows are imported. A `parent` not in the file makes a
+//!   top-level task.
+//!
+//! In all three an `est:N` token anywhere in the name sets the estimate, a
+//! `due:YYYY-MM-DD` token the due date, as in todo.txt, and `rec:RULE` how
+//! the task repeats (see `Recurrence`). `#words` become tags. They are all
+//! removed from the name.
+use crate::error::{read_file, Error, Result};
+use crate::model::{now, parse_date, split_tags, Task, DEFAULT_PRIORITY};
+use crate::recurrence::Recurrence;
+use crate::store::Store;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use time::format_description::well_known::Rfc3339;
+use time::{Date, OffsetDateTime};
+
+/// Tasks read from a file, not yet stored. Their `id` and `parent` are ids
+/// in the file, 0 and `None` when it has none, and their `project` is an
+/// index into `projects`. `import` maps them to the store's.
+#[derive(Debug, Default)]
+pub struct Parsed {
+    pub tasks: Vec<Task>,
+    pub projects: Vec<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    TodoTxt,
+    Markdown,
+    Csv,
+}
+
+impl Format {
+    pub fn from_path(path: &Path) -> Option<Format> {
+        let extension = path.extension()?.to_str()?.to_lowercase();
+        match extension.as_str() {
+            "txt" => Some(Format::TodoTxt),
+            "md" | "markdown" => Some(Format::Markdown),
+            "csv" => Some(Format::Csv),
+            _ => None,
+        }
+    }
+}
+
+fn new_task(name: &str, done: bool, estimate: i32) -> Task {
+    let mut task = Task {
+        done,
+        estimate,
+        ..Task::new("")
+    };
+    let mut words: Vec<&str> = vec![];
+    for word in name.split_whitespace() {
+        if let Some(Ok(estimate)) = word.strip_prefix("est:").map(str::parse) {
+            task.estimate = estimate;
+        } else if let Some(due) = word.strip_prefix("due:").and_then(parse_date) {
+            task.due = Some(due);
+        } else if let Some(recurrence) = word.strip_prefix("rec:").and_then(Recurrence::parse) {
+            task.recurrence = Some(recurrence);
+        } else {
+            words.push(word);
+        }
+    }
+    (task.name, task.tags) = split_tags(&words.join(" "));
+    task
+}
+
+fn local_midnight(date: Date) -> OffsetDateTime {
+    date.midnight().assume_offset(now().offset())
+}
+
+/// The priority of a todo.txt `(A)` marker, or `None` if `word` isn't one.
+fn todo_txt_priority(word: &str) -> Option<u8> {
+    match word.as_bytes() {
+        [b'(', letter @ b'A'..=b'Z', b')'] => Some((letter - b'A' + 1).min(DEFAULT_PRIORITY)),
+        _ => None,
+    }
+}
+
+fn parse_todo_txt(text: &str) -> Vec<Task> {
+    let mut tasks: Vec<Task> = vec![];
+    for line in text.lines() {
+        let mut words = line.split_whitespace().peekable();
+        let done = words.next_if_eq(&"x").is_some();
+        let priority = words.peek().and_then(|word| todo_txt_priority(word));
+        if priority.is_some() {
+            words.next();
+        }
+        let mut dates: Vec<Date> = vec![];
+        while let Some(date) = words.peek().and_then(|word| parse_date(word)) {
+            dates.push(date);
+            words.next();
+        }
+        let name: Vec<&str> = words.collect();
+        if !name.is_empty() {
+            let mut task = new_task(&name.join(" "), done, 0);
+            task.priority = priority.unwrap_or(DEFAULT_PRIORITY);
+            // The first date of a done task is when it was completed.
+            if done {
+                task.completed_at = dates.first().map(|date| local_midnight(*date));
+            }
+            tasks.push(task);
+        }
+    }
+    tasks
+}
+
+fn parse_markdown(text: &str) -> Vec<Task> {
+    let mut tasks: Vec<Task> = vec![];
+    for line in text.lines() {
+        let Some(item) = ["- [", "* [", "+ ["]
+            .iter()
+            .find_map(|bullet| line.trim_start().strip_prefix(bullet))
+        else {
+            continue;
+        };
+        let (done, name) = match item.split_once(']') {
+            Some((" ", name)) => (false, name),
+            Some(("x" | "X", name)) => (true, name),
+            _ => continue,
+        };
+        if !name.trim().is_empty() {
+            tasks.push(new_task(name, done, 0));
+        }
+    }
+    tasks
+}
+
+/// Splits CSV into records, honouring quoted fields with commas, doubled
+/// quotes and line breaks in them. Each record comes with the line it starts
+/// on, counting from 1.
+fn csv_records(text: &str) -> Vec<(usize, Vec<String>)> {
+    let mut records: Vec<(usize, Vec<String>)> = vec![];
+    let mut record: Vec<String> = vec![];
+    let mut field = String::new();
+    let mut quoted = false;
+    let (mut line, mut start) = (1, 1);
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '\n' {
+            line += 1;
+        }
+        match (quoted, c) {
+            (true, '"') if chars.next_if_eq(&'"').is_some() => field.push('"'),
+            (true, '"') => quoted = false,
+            (true, c) => field.push(c),
+            (false, '"') => quoted = true,
+            (false, ',') => record.push(std::mem::take(&mut field)),
+            (false, '\r') => {}
+            (false, '\n') => {
+                record.push(std::mem::take(&mut field));
+                records.push((start, std::mem::take(&mut record)));
+                start = line;
+            }
+            (false, c) => field.push(c),
+        }
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push((start, record));
+    }
+    records
+}
+
+/// `value` as a task id, 0 or `None` when empty.
+fn csv_id(value: &str, what: &str, line: usize) -> Result<Option<i32>> {
+    if value.is_empty() {
+        return Ok(None);
+    }
+    value.parse().map(Some).map_err(|_| {
+        Error::Import(format!(
+            "line {}: {} must be a task id, got '{}'",
+            line, what, value
+        ))
+    })
+}
+
+fn parse_csv(text: &str) -> Result<Parsed> {
+    let mut records = csv_records(text).into_iter();
+    let header: Vec<String> = records
+        .next()
+        .unwrap_or_default()
+        .1
+        .iter()
+        .map(|column| column.trim().to_lowercase())
+        .collect();
+    let column = |name: &str| header.iter().position(|column| column == name);
+    let name_column =
+        column("name").ok_or_else(|| Error::Import("CSV has no 'name' column".to_string()))?;
+    let (record_column, done_column, estimate_column, completed_column) = (
+        column("record"),
+        column("done"),
+        column("estimate"),
+        column("completed_at"),
+    );
+    let (due_column, planned_column, recurrence_column, tags_column, priority_column) = (
+        column("due"),
+        column("planned"),
+        column("recurrence"),
+        column("tags"),
+        column("priority"),
+    );
+    let (notes_column, id_column, parent_column, project_column) = (
+        column("notes"),
+        column("id"),
+        column("parent"),
+        column("project"),
+    );
+
+    let mut parsed = Parsed::default();
+    for (line, record) in records {
+        let field = |column: Option<usize>| {
+            column
+                .and_then(|column| record.get(column))
+                .map(|field| field.trim())
+                .unwrap_or_default()
+        };
+        if record_column.is_some() && field(record_column) != "task" {
+            continue;
+        }
+        let name = field(Some(name_column));
+        if name.is_empty() {
+            continue;
+        }
+        let done = matches!(
+            field(done_column).to_lowercase().as_str(),
+            "true" | "yes" | "x" | "1"
+        );
+        let estimate = match field(estimate_column) {
+            "" => 0,
+            estimate => estimate.parse().map_err(|_| {
+                Error::Import(format!(
+                    "line {}: estimate must be a number, got '{}'",
+                    line, estimate
+                ))
+            })?,
+        };
+        let mut task = new_task(name, done, estimate);
+        if done {
+            let completed_at = field(completed_column);
+            task.completed_at = OffsetDateTime::parse(completed_at, &Rfc3339)
+                .ok()
+                .or_else(|| parse_date(completed_at).map(local_midnight));
+        }
+        task.due = parse_date(field(due_column)).or(task.due);
+        task.planned = parse_date(field(planned_column));
+        task.recurrence = Recurrence::parse(field(recurrence_column)).or(task.recurrence);
+        for tag in field(tags_column).split_whitespace() {
+            task.tags.push(tag.trim_start_matches('#').to_string());
+        }
+        task.tags.sort();
+        task.tags.dedup();
+        let priority = field(priority_column);
+        if !priority.is_empty() {
+            task.priority = match priority.trim_start_matches(['P', 'p']).parse() {
+                Ok(priority @ 1..=4) => priority,
+                _ => {
+                    return Err(Error::Import(format!(
+                        "line {}: priority must be 1 to 4, got '{}'",
+                        line, priority
+                    )))
+                }
+            };
+        }
+        task.notes = field(notes_column).to_string();
+        task.id = csv_id(field(id_column), "id", line)?.unwrap_or(0);
+        task.parent = csv_id(field(parent_column), "parent", line)?;
+        let project = field(project_column);
+        if !project.is_empty() {
+            let index = match parsed.projects.iter().position(|name| name == project) {
+                Some(index) => index,
+                None => {
+                    parsed.projects.push(project.to_string());
+                    parsed.projects.len() - 1
+                }
+            };
+            task.project = Some(index as i32);
+        }
+        parsed.tasks.push(task);
+    }
+    Ok(parsed)
+}
+
+/// Tasks described by `text`, not yet stored.
+pub fn parse(format: Format, text: &str) -> Result<Parsed> {
+    let tasks = match format {
+        Format::TodoTxt => parse_todo_txt(text),
+        Format::Markdown => parse_markdown(text),
+        Format::Csv => return parse_csv(text),
+    };
+    Ok(Parsed {
+        tasks,
+        projects: vec![],
+    })
+}
+
+/// Reads and parses `path`, picking the format from its extension.
+pub fn parse_file<P: AsRef<Path>>(path: P) -> Result<Parsed> {
+    let path = path.as_ref();
+    let format = Format::from_path(path).ok_or_else(|| {
+        Error::Import(format!(
+            "{}: unknown file type, expected .txt, .md or .csv",
+            path.display()
+        ))
+    })?;
+    parse(format, &read_file(path)?)
+}
+
+/// Id of the project named `name`, created if there is none.
+fn project_id(store: &mut dyn Store, name: &str) -> Result<i32> {
+    let find = |store: &mut dyn Store| -> Result<Option<i32>> {
+        let projects = store.get_projects()?;
+        Ok(projects
+            .into_iter()
+            .find(|project| project.name == name)
+            .map(|project| project.id))
+    };
+    if let Some(id) = find(store)? {
+        return Ok(id);
+    }
+    store.create_project(name.to_string())?;
+    find(store)?.ok_or_else(|| Error::Import(format!("could not create project '{}'", name)))
+}
+
+/// Stores `parsed`, parents before their subtasks whatever the order in the
+/// file.
+pub fn import(store: &mut dyn Store, parsed: &Parsed) -> Result<()> {
+    let mut projects = vec![];
+    for name in parsed.projects.iter() {
+        projects.push(project_id(store, name)?);
+    }
+    let in_file: HashSet<i32> = parsed.tasks.iter().map(|task| task.id).collect();
+    // Ids in the file of the tasks stored so far, to the store's.
+    let mut ids: HashMap<i32, i32> = HashMap::new();
+    let mut pending: Vec<&Task> = parsed.tasks.iter().collect();
+    while !pending.is_empty() {
+        let (ready, waiting): (Vec<&Task>, Vec<&Task>) = pending.into_iter().partition(|task| {
+            task.parent
+                .is_none_or(|parent| ids.contains_key(&parent) || !in_file.contains(&parent))
+        });
+        if ready.is_empty() {
+            return Err(Error::Import(
+                "subtasks can't be their own parents".to_string(),
+            ));
+        }
+        for task in ready {
+            let id = store.create_task(Task {
+                id: 0,
+                project: task.project.map(|index| projects[index as usize]),
+                parent: task.parent.and_then(|parent| ids.get(&parent).copied()),
+                ..task.clone()
+            })?;
+            if task.id != 0 {
+                ids.insert(task.id, id);
+            }
+        }
+        pending = waiting;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn summary(tasks: &[Task]) -> Vec<(&str, bool, i32)> {
+        tasks
+            .iter()
+            .map(|task| (task.name.as_str(), task.done, task.estimate))
+            .collect()
+    }
+
+    #[test]
+    fn todo_txt_drops_markers_and_reads_estimates() {
+        let tasks = parse(
+            Format::TodoTxt,
+            "(A) 2024-05-01 Write report est:3 +work #q2 due:2024-05-03\n\nx 2024-05-02 2024-05-01 Call Bob\n",
+        )
+        .unwrap()
+        .tasks;
+        assert_eq!(
+            summary(&tasks),
+            [("Write report +work", false, 3), ("Call Bob", true, 0)]
+        );
+        assert_eq!(tasks[0].completed_at, None);
+        assert_eq!(tasks[0].due, parse_date("2024-05-03"));
+        assert_eq!(tasks[0].tags, ["q2"]);
+        assert_eq!(
+            (tasks[0].priority, tasks[1].priority),
+            (1, DEFAULT_PRIORITY)
+        );
+        assert_eq!(
+            tasks[1]
+                .completed_at
+                .map(|completed_at| completed_at.date()),
+            parse_date("2024-05-02")
+        );
+    }
+
+    #[test]
+    fn markdown_reads_only_checklist_items() {
+        let tasks = parse(
+            Format::Markdown,
+            "# Backlog\n- [ ] Write report est:2 rec:weekly:fri\n  * [x] Call Bob\n- plain item\n",
+        )
+        .unwrap()
+        .tasks;
+        assert_eq!(
+            summary(&tasks),
+            [("Write report", false, 2), ("Call Bob", true, 0)]
+        );
+        assert_eq!(tasks[0].recurrence, Recurrence::parse("weekly:fri"));
+    }
+
+    #[test]
+    fn csv_reads_named_columns_and_skips_timer_rows() {
+        let tasks = parse(
+            Format::Csv,
+            "record,id,name,done,estimate,notes\ntask,1,\"Call \"\"Bob\"\", later\",true,2,\"Ask about:\n- the budget\"\ntimer,1,,,,\ntask,2,Review est:4,false,,\n",
+        )
+        .unwrap()
+        .tasks;
+        assert_eq!(
+            summary(&tasks),
+            [("Call \"Bob\", later", true, 2), ("Review", false, 4)]
+        );
+        assert_eq!(tasks[0].notes, "Ask about:\n- the budget");
+    }
+
+    #[test]
+    fn csv_without_name_column_is_an_error() {
+        assert!(parse(Format::Csv, "title\nWrite report\n").is_err());
+    }
+
+    #[test]
+    fn csv_errors_name_the_line_in_the_file() {
+        let error = parse(
+            Format::Csv,
+            "name,notes,estimate\nWrite report,\"two\nlines\",1\nReview,,many\n",
+        )
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "line 4: estimate must be a number, got 'many'"
+        );
+    }
+
+    #[test]
+    fn csv_export_keeps_projects_and_subtasks() {
+        // The subtask comes first, as it may in an export.
+        let parsed = parse(
+            Format::Csv,
+            "id,name,project,parent\n7,Outline,Thesis,5\n5,Write thesis,Thesis,\n9,Groceries,,3\n",
+        )
+        .unwrap();
+        assert_eq!(parsed.projects, ["Thesis"]);
+        let mut store = crate::store::InMemoryStore::new();
+        store.create_project("Thesis".to_string()).unwrap();
+        import(&mut store, &parsed).unwrap();
+
+        let project = store.get_projects().unwrap()[0].id;
+        assert_eq!(store.get_projects().unwrap().len(), 1);
+        let tasks = store.get_all_tasks().unwrap();
+        let find = |name: &str| tasks.iter().find(|task| task.name == name).unwrap();
+        let thesis = find("Write thesis");
+        assert_eq!(thesis.project, Some(project));
+        assert_eq!(find("Outline").parent, Some(thesis.id));
+        // A parent that isn't in the file leaves a top-level task.
+        assert_eq!(
+            (find("Groceries").parent, find("Groceries").project),
+            (None, None)
+        );
+    }
+}
@@ -0,0 +1,13 @@
+//! Tasks, timers and their storage, shared by the GUI and the `pomo`
+//! command-line tool.
+pub mod config;
+pub mod cycle;
+pub mod error;
+pub mod export;
+pub mod import;
+pub mod migrations;
+pub mod model;
+pub mod recurrence;
+pub mod stats;
+pub mod store;
+pub mod undo;
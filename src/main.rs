@@ -1,48 +1,65 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")] // hide console window on Windows in release
-use eframe::egui::{self, Button, Color32, ImageButton, Label, RichText};
+use banner::ErrorBanner;
+use eframe::egui::{self, Button, Color32, ImageButton, RichText};
+use pomodoro_todo_list::config::{Configuration, CONFIGURATION_PATH};
+use pomodoro_todo_list::cycle::Cycle;
+use pomodoro_todo_list::error::{open_file, Result};
+use pomodoro_todo_list::export::{export_to_file, Format};
+use pomodoro_todo_list::import::{import as import_tasks, parse_file, Parsed};
+use pomodoro_todo_list::model::{
+    cycle_priority, interruption_marks, now, parse_date, split_tags, task_tree, Interruption,
+    InterruptionKind, Project, Task, Timer, TimerKind, TimerStatus, DEFAULT_PRIORITY,
+};
+use pomodoro_todo_list::recurrence::Recurrence;
+use pomodoro_todo_list::stats::{suggested_estimate, EstimateRecord, Statistics};
+use pomodoro_todo_list::store::{InMemoryStore, SqliteStore, Store, DATABASE_PATH};
+use pomodoro_todo_list::undo::History;
 use rodio::{source::Source, Decoder, OutputStream};
-use rusqlite::{Connection, Result};
+use std::cmp::max;
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::io::BufReader;
-use std::path::Path;
-use std::{cmp::max, fs::File};
-use time::{Date, Duration, Month, OffsetDateTime, Time, UtcOffset};
-fn setup_database() -> Result<Connection> {
-    let conn = Connection::open("tasks.db")?;
-    //conn.execute("DROP TABLE IF EXISTS tasks", ())?;
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS tasks (
-            id    INTEGER PRIMARY KEY,
-            name  TEXT NOT NULL,
-            done INTEGER,
-            estimate INTEGER,
-            locked INTEGER,
-            just_created INTEGER
-        )",
-        (),
-    )?;
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS timers (
-            id    INTEGER PRIMARY KEY,
-            is_pomodoro  INTEGER,
-            start INTEGER,
-            duration INTEGER,
-            task INTEGER,
-            FOREIGN KEY(task) REFERENCES tasks(id)
-        )",
-        (),
-    )?;
-    Ok(conn)
+use time::macros::format_description;
+use time::{Date, Duration, OffsetDateTime, Weekday};
+
+mod banner;
+mod markdown;
+mod stats_view;
+
+const DEFAULT_WINDOW_TITLE: &str = "Pomodoro To Do List";
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum View {
+    Tasks,
+    /// Open tasks planned or due today, and overdue ones.
+    Today,
+    Done,
+    Statistics,
 }
-use serde::Deserialize;
-
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "snake_case")]
-struct Configuration {
-    focus_duration: i32,
-    short_break_duration: i32,
-    long_break_duration: i32,
+
+/// Which tasks the task list shows.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum ProjectFilter {
+    All,
+    /// One project, or the inbox with `None`.
+    Only(Option<i32>),
+}
+
+impl ProjectFilter {
+    fn shows(&self, task: &Task) -> bool {
+        match self {
+            ProjectFilter::All => true,
+            ProjectFilter::Only(project) => task.project == *project,
+        }
+    }
+}
+
+/// State of the import window, which is open while `MyApp::import` is set.
+#[derive(Default)]
+struct ImportDialog {
+    path: String,
+    /// Parsed tasks, shown for review before anything is stored.
+    preview: Option<Parsed>,
 }
-const DEFAULT_WINDOW_TITLE: &str = "Pomodoro To Do List";
 
 fn main() -> eframe::Result {
     env_logger::init();
@@ -61,587 +78,2094 @@ fn main() -> eframe::Result {
     )
 }
 
-struct Task {
-    id: i32,
-    name: String,
-    done: bool,
-    estimate: i32,
-    locked: bool,
-    just_created: bool,
-}
-
-struct Timer {
-    id: i32,
-    is_pomodoro: bool,
-    start: OffsetDateTime,
-    duration: i32,
-    task: Option<i32>,
-}
-
 struct MyApp {
-    conn: Connection,
+    store: History,
+    errors: ErrorBanner,
     show_new_task_input: bool,
     new_task_name: String,
     tasks: Vec<Task>,
-    played_notification: bool,
+    projects: Vec<Project>,
+    project_filter: ProjectFilter,
+    /// Tasks whose subtasks are hidden.
+    collapsed: HashSet<i32>,
+    /// Task moved by Alt+Up/Alt+Down and reprioritised by Alt+P, picked by
+    /// clicking its name.
+    selected_task: Option<i32>,
+    /// Task focus timers are started for and credited to once over.
+    current_task: Option<i32>,
+    /// Tasks whose notes panel is open under their row.
+    open_notes: HashSet<i32>,
+    /// Task whose notes are being edited, with the text so far. Saved as a
+    /// whole, so the edit is undone in one step.
+    notes_draft: Option<(i32, String)>,
+    /// Name typed for a new project, while the input is shown.
+    new_project_name: Option<String>,
+    /// Day typed into the "Due" or "Plan for" menu of a task, with the task
+    /// id and menu it was typed into.
+    day_input: (i32, &'static str, String),
+    /// Interval picked in the "Repeat" menu of a task.
+    repeat_days: u32,
+    /// Tags picked in the filter bar; tasks with any of them are shown.
+    tag_filter: BTreeSet<String>,
+    /// Timer the end-of-timer notification was played for, so it plays once
+    /// per timer however it was started.
+    notified_timer: Option<i32>,
+    /// Note typed in the "Interrupted" menu of the running focus timer.
+    interruption_note: String,
+    /// Reason typed when stopping a focus timer without crediting it.
+    stop_reason: String,
     pomodoros_estimate: i32,
     last_checked_time: OffsetDateTime,
     timer_value: String,
     configuration: Configuration,
     timers_queue: Vec<Timer>,
+    cycle: Cycle,
+    /// Next step of the cycle, offered as a button when it isn't auto-started.
+    next_timer: Option<TimerKind>,
+    view: View,
+    statistics: Statistics,
+    /// Tasks listed in the Done view, filtered by `done_from`/`done_to`.
+    done_tasks: Vec<Task>,
+    done_from: String,
+    done_to: String,
+    estimates: Vec<EstimateRecord>,
+    suggested_estimate: Option<i32>,
+    /// Totals shown next to projects and tasks, loaded with the tasks rather
+    /// than queried every frame. Keyed by project, `None` being the inbox.
+    project_pomodoros: HashMap<Option<i32>, usize>,
+    /// Pomodoros including subtasks, keyed by task, open or done.
+    task_pomodoros: HashMap<i32, usize>,
+    /// Estimates including subtasks, keyed by task.
+    task_estimates: HashMap<i32, i32>,
+    task_interruptions: HashMap<i32, Vec<Interruption>>,
+    /// Where the last export from the menu went.
+    exported_to: Option<String>,
+    import: Option<ImportDialog>,
+    /// Tasks are re-read periodically so changes made by `pomo` show up.
+    last_reload: OffsetDateTime,
+    /// What was last edited, undone or redone, and when. Only new edits
+    /// get an "Undo" button.
+    undo_toast: Option<(String, OffsetDateTime, bool)>,
+    /// `History::generation` when the toast was last updated.
+    seen_generation: u64,
 }
 
 impl Default for MyApp {
     fn default() -> Self {
+        let mut errors = ErrorBanner::default();
+        let store: Box<dyn Store> = match errors.report(
+            "Could not open tasks.db, changes won't be saved",
+            SqliteStore::open(DATABASE_PATH),
+        ) {
+            Some(store) => Box::new(store),
+            None => Box::new(InMemoryStore::new()),
+        };
+        let configuration = errors
+            .report(
+                "Could not load configuration, using defaults",
+                Configuration::load(CONFIGURATION_PATH),
+            )
+            .unwrap_or_default();
+        MyApp::new(store, configuration, errors)
+    }
+}
+
+impl MyApp {
+    fn new(store: Box<dyn Store>, configuration: Configuration, errors: ErrorBanner) -> Self {
         let mut self_setup = Self {
-            conn: setup_database().unwrap(),
+            store: History::new(store),
+            errors,
             show_new_task_input: false,
             new_task_name: "".to_string(),
             tasks: vec![],
-            played_notification: false,
+            projects: vec![],
+            project_filter: ProjectFilter::All,
+            collapsed: HashSet::new(),
+            selected_task: None,
+            current_task: None,
+            open_notes: HashSet::new(),
+            notes_draft: None,
+            new_project_name: None,
+            day_input: (0, "", String::new()),
+            repeat_days: 2,
+            tag_filter: BTreeSet::new(),
+            notified_timer: None,
+            interruption_note: String::new(),
+            stop_reason: String::new(),
             pomodoros_estimate: 0,
-            last_checked_time: OffsetDateTime::now_local().unwrap(),
+            last_checked_time: now(),
             timer_value: "".to_string(),
-            configuration: Configuration {
-                focus_duration: 20,
-                short_break_duration: 10,
-                long_break_duration: 30,
-            },
+            configuration,
             timers_queue: vec![],
+            cycle: Cycle::default(),
+            next_timer: None,
+            view: View::Tasks,
+            statistics: Statistics::default(),
+            done_tasks: vec![],
+            done_from: "".to_string(),
+            done_to: "".to_string(),
+            estimates: vec![],
+            suggested_estimate: None,
+            project_pomodoros: HashMap::new(),
+            task_pomodoros: HashMap::new(),
+            task_estimates: HashMap::new(),
+            task_interruptions: HashMap::new(),
+            exported_to: None,
+            import: None,
+            last_reload: now(),
+            undo_toast: None,
+            seen_generation: 0,
         };
-        let json_file_path = Path::new("./configuration.json");
-        let file = File::open(json_file_path).expect("configuration file not found");
-        self_setup.configuration =
-            serde_json::from_reader(file).expect("JSON was not well-formatted");
-        self_setup.tasks = get_tasks(&self_setup.conn);
-        self_setup.pomodoros_estimate = get_pomodoros_median(&mut self_setup.conn);
-
+        self_setup.timers_queue = self_setup
+            .errors
+            .report("Could not load queue", self_setup.store.get_queue())
+            .unwrap_or_default();
+        self_setup.reload();
         self_setup
     }
-}
 
-fn play_notificaiton() {
-    let (_stream, stream_handle) = OutputStream::try_default().unwrap();
-    let file = BufReader::new(File::open("assets/notification.mp3").unwrap());
-    let source = Decoder::new(file).unwrap();
-    stream_handle.play_raw(source.convert_samples()).unwrap();
-    std::thread::sleep(std::time::Duration::from_millis(1000));
-}
+    fn refresh(&mut self) -> Result<()> {
+        self.tasks = self.store.get_tasks()?;
+        self.projects = self.store.get_projects()?;
+        self.pomodoros_estimate = self.store.get_pomodoros_median()?;
+        self.estimates = self.store.get_estimate_history()?;
+        self.suggested_estimate = suggested_estimate(&self.estimates);
+        self.refresh_totals()
+    }
 
-fn get_tasks(conn: &Connection) -> Vec<Task> {
-    let mut tasks: Vec<Task> = vec![];
-    let mut stmt = conn.prepare("SELECT * FROM tasks where done = 0").unwrap();
-    let tasks_iter = stmt
-        .query_map([], |row| {
-            Ok(Task {
-                id: row.get(0)?,
-                name: row.get(1)?,
-                done: row.get(2)?,
-                estimate: row.get(3)?,
-                locked: row.get(4)?,
-                just_created: row.get(5)?,
-            })
-        })
-        .unwrap();
-    for task in tasks_iter {
-        tasks.push(task.unwrap());
+    fn refresh_totals(&mut self) -> Result<()> {
+        self.project_pomodoros.clear();
+        let projects = self.projects.iter().map(|project| Some(project.id));
+        for project in projects.chain([None]) {
+            let pomodoros = self.store.get_project_pomodoros(project)?;
+            self.project_pomodoros.insert(project, pomodoros);
+        }
+        self.task_pomodoros.clear();
+        for task in self.tasks.iter().chain(self.done_tasks.iter()) {
+            let pomodoros = self.store.get_task_pomodoros(task.id)?;
+            self.task_pomodoros.insert(task.id, pomodoros);
+        }
+        self.task_estimates.clear();
+        self.task_interruptions.clear();
+        for task in self.tasks.iter() {
+            let estimate = self.store.get_task_estimate(task.id)?;
+            self.task_estimates.insert(task.id, estimate);
+            let interruptions = self.store.get_task_interruptions(task.id)?;
+            self.task_interruptions.insert(task.id, interruptions);
+        }
+        Ok(())
     }
-    tasks
-}
 
-fn get_running_timers(conn: &mut Connection) -> Vec<Timer> {
-    let mut timers: Vec<Timer> = vec![];
-    let mut stmt = conn
-        .prepare("SELECT * FROM timers where task is NULL")
-        .unwrap();
-    let timers_iter = stmt
-        .query_map([], |row| {
-            Ok(Timer {
-                id: row.get(0)?,
-                is_pomodoro: row.get(1)?,
-                start: row.get(2)?,
-                duration: row.get(3)?,
-                task: row.get(4)?,
-            })
-        })
-        .unwrap();
-    for timer in timers_iter {
-        timers.push(timer.unwrap());
+    fn reload(&mut self) {
+        let result = self.refresh();
+        self.errors.report("Could not load tasks", result);
+        self.last_reload = now();
     }
-    timers
-}
 
-fn set_task_status(conn: &mut Connection, done: bool, id: i32) {
-    let tx = conn.transaction().unwrap();
-    tx.execute("UPDATE tasks SET done = ?1 where id = ?2", (done, id))
-        .unwrap();
-    tx.commit().unwrap();
-}
+    /// Picks up changes made outside the GUI, unless a task name is being
+    /// edited, where reloading would throw the typed text away.
+    fn reload_if_stale(&mut self, now: OffsetDateTime) {
+        let editing = self.tasks.iter().any(|task| !task.locked);
+        if !editing && now - self.last_reload >= Duration::seconds(2) {
+            self.reload();
+        }
+    }
 
-fn set_task_locked(conn: &mut Connection, locked: bool, id: i32) {
-    let tx = conn.transaction().unwrap();
-    tx.execute("UPDATE tasks SET locked = ?1 where id = ?2", (locked, id))
-        .unwrap();
-    tx.commit().unwrap();
-}
+    fn reload_statistics(&mut self) {
+        let since = now().date() - Duration::days(365);
+        if let Some(statistics) = self.errors.report(
+            "Could not load statistics",
+            self.store.get_statistics(since),
+        ) {
+            self.statistics = statistics;
+        }
+    }
 
-fn set_task_just_created(conn: &mut Connection, just_created: bool, id: i32) {
-    let tx = conn.transaction().unwrap();
-    tx.execute(
-        "UPDATE tasks SET just_created = ?1 where id = ?2",
-        (just_created, id),
-    )
-    .unwrap();
-    tx.commit().unwrap();
-}
-fn set_task_name(conn: &mut Connection, name: String, id: i32) {
-    let tx = conn.transaction().unwrap();
-    tx.execute("UPDATE tasks SET name = ?1 where id = ?2", (name, id))
-        .unwrap();
-    tx.commit().unwrap();
-}
+    fn reload_done(&mut self) {
+        if let Some(tasks) = self.errors.report(
+            "Could not load done tasks",
+            self.store
+                .get_done_tasks(parse_date(&self.done_from), parse_date(&self.done_to)),
+        ) {
+            self.done_tasks = tasks;
+        }
+        let result = self.refresh_totals();
+        self.errors.report("Could not load totals", result);
+    }
 
-fn set_task_estimate(conn: &mut Connection, estimate: i32, id: i32) {
-    let tx = conn.transaction().unwrap();
-    tx.execute(
-        "UPDATE tasks SET estimate = ?1 where id = ?2",
-        (estimate, id),
-    )
-    .unwrap();
-    tx.commit().unwrap();
-}
+    /// Starts the next queued timer if nothing is running. Returns whether one was started.
+    fn start_queued_timer(&mut self, now: OffsetDateTime) -> Result<bool> {
+        if !self.store.get_running_timers()?.is_empty() {
+            return Ok(false);
+        }
+        if self.timers_queue.is_empty() {
+            return Ok(false);
+        }
+        let mut timer = self.timers_queue.remove(0);
+        timer.start = now;
+        if timer.is_pomodoro() {
+            timer.task = self.current_task;
+        }
+        self.store.create_timer(timer)?;
+        self.store.save_queue(&self.timers_queue)?;
+        Ok(true)
+    }
 
-fn delete_task(conn: &mut Connection, id: i32) {
-    let tx = conn.transaction().unwrap();
-    tx.execute("DELETE from tasks where id = ?1", [id]).unwrap();
-    tx.commit().unwrap();
-}
+    fn save_queue(&mut self) {
+        let result = self.store.save_queue(&self.timers_queue);
+        self.errors.report("Could not save queue", result);
+    }
+
+    fn show_queue(&mut self, ctx: &egui::Context) {
+        if self.timers_queue.is_empty() {
+            return;
+        }
+        let mut moved = None;
+        let mut removed = None;
+        egui::SidePanel::right("queue").show(ctx, |ui| {
+            ui.heading("Queue");
+            for (index, timer) in self.timers_queue.iter().enumerate() {
+                ui.horizontal(|ui| {
+                    let response = ui
+                        .dnd_drag_source(egui::Id::new(("queue", index)), index, |ui| {
+                            ui.label(format!(
+                                "{}. {} ({} min)",
+                                index + 1,
+                                timer.kind.label(),
+                                timer.duration
+                            ));
+                        })
+                        .response
+                        .on_hover_cursor(egui::CursorIcon::Grab);
+                    if let Some(from) = response.dnd_release_payload::<usize>() {
+                        moved = Some((*from, index));
+                    }
+                    if ui
+                        .add(egui::Button::frame(egui::Button::new("x"), false))
+                        .on_hover_cursor(egui::CursorIcon::PointingHand)
+                        .clicked()
+                    {
+                        removed = Some(index);
+                    }
+                });
+            }
+        });
+        if let Some((from, to)) = moved {
+            let timer = self.timers_queue.remove(from);
+            self.timers_queue.insert(to, timer);
+            self.save_queue();
+        }
+        if let Some(index) = removed {
+            self.timers_queue.remove(index);
+            self.save_queue();
+        }
+    }
 
-fn mean(numbers: &Vec<i32>) -> f32 {
-    let sum: i32 = numbers.iter().sum();
+    fn show_projects(&mut self, ctx: &egui::Context) {
+        let mut update_ui = false;
+        egui::SidePanel::left("projects").show(ctx, |ui| {
+            ui.heading("Projects");
+            let mut filters = vec![
+                (ProjectFilter::All, "All".to_string()),
+                (ProjectFilter::Only(None), "Inbox".to_string()),
+            ];
+            for project in self.projects.iter() {
+                filters.push((ProjectFilter::Only(Some(project.id)), project.name.clone()));
+            }
+            for (filter, name) in filters {
+                let label = match filter {
+                    ProjectFilter::All => name,
+                    ProjectFilter::Only(project) => {
+                        let pomodoros = self.project_pomodoros.get(&project).copied();
+                        format!("{} ({})", name, pomodoros.unwrap_or_default())
+                    }
+                };
+                ui.selectable_value(&mut self.project_filter, filter, label);
+            }
+            match &mut self.new_project_name {
+                Some(name) => {
+                    let response =
+                        ui.add(egui::TextEdit::singleline(name).hint_text("Project name..."));
+                    response.request_focus();
+                    if response.lost_focus() {
+                        let name = name.trim().to_string();
+                        if !name.is_empty() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                            self.errors.report(
+                                "Could not create project",
+                                self.store.create_project(name),
+                            );
+                            update_ui = true;
+                        }
+                        self.new_project_name = None;
+                    }
+                }
+                None => {
+                    if ui
+                        .add(egui::Button::frame(
+                            egui::Button::new("+ Add Project"),
+                            false,
+                        ))
+                        .on_hover_cursor(egui::CursorIcon::PointingHand)
+                        .clicked()
+                    {
+                        self.new_project_name = Some(String::new());
+                    }
+                }
+            }
+        });
+        if update_ui {
+            self.reload();
+        }
+    }
 
-    sum as f32 / numbers.len() as f32
-}
+    fn show_import(&mut self, ctx: &egui::Context) {
+        let Some(dialog) = &mut self.import else {
+            return;
+        };
+        let mut close = false;
+        let mut import = false;
+        egui::Window::new("Import tasks")
+            .collapsible(false)
+            .show(ctx, |ui| {
+                ui.label("todo.txt (.txt), Markdown checklist (.md) or CSV (.csv) file");
+                ui.horizontal(|ui| {
+                    ui.text_edit_singleline(&mut dialog.path);
+                    if ui.button("Preview").clicked() {
+                        dialog.preview = self
+                            .errors
+                            .report("Could not read import file", parse_file(&dialog.path));
+                    }
+                });
+                if let Some(Parsed { tasks, .. }) = &dialog.preview {
+                    ui.separator();
+                    egui::ScrollArea::vertical()
+                        .max_height(200.0)
+                        .show(ui, |ui| {
+                            for task in tasks {
+                                let check = if task.done { "[x]" } else { "[ ]" };
+                                if task.estimate > 0 {
+                                    ui.label(format!(
+                                        "{} {} ({} estimated)",
+                                        check, task.name, task.estimate
+                                    ));
+                                } else {
+                                    ui.label(format!("{} {}", check, task.name));
+                                }
+                            }
+                        });
+                    ui.separator();
+                }
+                ui.horizontal(|ui| {
+                    if let Some(Parsed { tasks, .. }) = &dialog.preview {
+                        import = ui
+                            .add_enabled(
+                                !tasks.is_empty(),
+                                Button::new(format!("Import {} tasks", tasks.len())),
+                            )
+                            .clicked();
+                    }
+                    close = ui.button("Cancel").clicked();
+                });
+            });
+        if import {
+            if let Some(parsed) = dialog.preview.take() {
+                let result = self.store.batch(&[], |store| import_tasks(store, &parsed));
+                self.errors.report("Could not import tasks", result);
+                self.reload();
+            }
+            close = true;
+        }
+        if close {
+            self.import = None;
+        }
+    }
 
-fn median(numbers: &mut Vec<i32>) -> i32 {
-    numbers.sort();
+    fn queued_timer(&self, kind: TimerKind) -> Timer {
+        Timer::new(kind, self.configuration.duration(kind), now())
+    }
 
-    let mid = numbers.len() / 2;
-    if numbers.len() % 2 == 0 {
-        mean(&vec![numbers[mid - 1], numbers[mid]]) as i32
-    } else {
-        numbers[mid]
+    fn queue_timer(&mut self, kind: TimerKind) {
+        self.timers_queue.push(self.queued_timer(kind));
+        self.next_timer = None;
+        self.save_queue();
     }
-}
 
-fn get_pomodoros_median(conn: &mut Connection) -> i32 {
-    let mut pomodoros: Vec<i32> = vec![];
-    let mut stmt = conn
-        .prepare("select count(start) from timers where start >= date('now','-30 days') and start < date('now') and time(start) >= time('now') group by date(start)")
-        .unwrap();
-    let pomodoros_iter = stmt.query_map([], |row| Ok(row.get(0)?)).unwrap();
-    for pomodoro_count in pomodoros_iter {
-        pomodoros.push(pomodoro_count.unwrap());
-    }
-    if pomodoros.len() == 0 {
-        0
-    } else {
-        median(&mut pomodoros)
+    /// Moves the cycle past a finished timer and schedules what comes next,
+    /// unless something is already queued.
+    fn schedule_next(&mut self, finished: TimerKind) {
+        let next = self
+            .cycle
+            .advance(finished, self.configuration.long_break_interval);
+        if !self.timers_queue.is_empty() {
+            return;
+        }
+        let auto_start = match next {
+            TimerKind::Focus => self.configuration.auto_start_focus,
+            TimerKind::ShortBreak | TimerKind::LongBreak => self.configuration.auto_start_breaks,
+        };
+        if auto_start {
+            self.queue_timer(next);
+        } else {
+            self.next_timer = Some(next);
+        }
     }
-}
 
-fn create_timer(conn: &mut Connection, timer: Timer) {
-    let tx = conn.transaction().unwrap();
-    match timer.task {
-        Some(task) => {
-            tx.execute(
-                "INSERT INTO timers (is_pomodoro, start, duration, task) VALUES (?1, ?2, ?3, ?4)",
-                (timer.is_pomodoro, timer.start, timer.duration, task),
-            )
-            .unwrap();
+    fn add_task(&mut self) -> Result<()> {
+        self.store.create_task(Task {
+            locked: false,
+            just_created: true,
+            project: match self.project_filter {
+                ProjectFilter::All => None,
+                ProjectFilter::Only(project) => project,
+            },
+            // Tasks added in the Today view are for today, or they'd vanish.
+            planned: (self.view == View::Today).then(|| now().date()),
+            ..Task::new(&self.new_task_name)
+        })?;
+        self.new_task_name = "".to_string();
+        self.show_new_task_input = true;
+        Ok(())
+    }
+
+    /// Ids of the tasks listed next to task `id`: same parent, same priority.
+    fn siblings(&self, id: i32) -> Vec<i32> {
+        let Some(task) = self.tasks.iter().find(|task| task.id == id) else {
+            return vec![];
+        };
+        self.tasks
+            .iter()
+            .filter(|other| other.parent == task.parent && other.priority == task.priority)
+            .map(|other| other.id)
+            .collect()
+    }
+
+    /// Moves task `id` to the place of `target` among their siblings and
+    /// stores the new order. Tasks with different parents or priorities
+    /// aren't reordered, the list is sorted by priority first.
+    fn move_task(&mut self, id: i32, target: i32) -> Result<()> {
+        let mut siblings = self.siblings(id);
+        let from = siblings.iter().position(|sibling| *sibling == id);
+        let to = siblings.iter().position(|sibling| *sibling == target);
+        if let (Some(from), Some(to)) = (from, to) {
+            siblings.remove(from);
+            siblings.insert(to, id);
+            self.store.set_task_order(&siblings)?;
         }
-        None => {
-            tx.execute(
-                "INSERT INTO timers (is_pomodoro, start, duration) VALUES (?1, ?2, ?3)",
-                (timer.is_pomodoro, timer.start, timer.duration),
-            )
-            .unwrap();
+        Ok(())
+    }
+
+    /// Whether `task` is listed under the current view, project and tag
+    /// filters.
+    fn is_listed(&self, task: &Task, today: Date) -> bool {
+        let shown = if self.view == View::Today {
+            task.is_for_today(today)
+        } else {
+            self.project_filter.shows(task)
+        };
+        // Rows being edited stay, or a new task would vanish while named.
+        let tagged = self.tag_filter.is_empty()
+            || !task.locked
+            || task.tags.iter().any(|tag| self.tag_filter.contains(tag));
+        shown && tagged
+    }
+
+    /// Swaps the selected task with its previous (`up`) or next sibling
+    /// among the listed ones; hidden siblings keep their places.
+    fn move_selected_task(&mut self, up: bool) -> Result<()> {
+        let Some(id) = self.selected_task else {
+            return Ok(());
+        };
+        let today = now().date();
+        let siblings: Vec<i32> = self
+            .siblings(id)
+            .into_iter()
+            .filter(|sibling| {
+                self.tasks
+                    .iter()
+                    .find(|task| task.id == *sibling)
+                    .is_some_and(|task| self.is_listed(task, today))
+            })
+            .collect();
+        let Some(index) = siblings.iter().position(|sibling| *sibling == id) else {
+            return Ok(());
+        };
+        let neighbour = if up {
+            index.checked_sub(1)
+        } else {
+            Some(index + 1)
+        };
+        match neighbour.and_then(|neighbour| siblings.get(neighbour)) {
+            Some(target) => self.move_task(id, *target),
+            None => Ok(()),
+        }
+    }
+
+    /// Gives the selected task the next priority, see `cycle_priority`.
+    fn cycle_selected_priority(&mut self) -> Result<()> {
+        let Some(task) = self
+            .tasks
+            .iter()
+            .find(|task| Some(task.id) == self.selected_task)
+        else {
+            return Ok(());
+        };
+        self.store
+            .set_task_priority(cycle_priority(task.priority), task.id)
+    }
+
+    /// Adds an unnamed subtask to `self.tasks[index]`, in the same project.
+    fn add_subtask(&mut self, index: usize) -> Result<()> {
+        let parent = &self.tasks[index];
+        self.collapsed.remove(&parent.id);
+        self.store.create_task(Task {
+            locked: false,
+            just_created: true,
+            project: parent.project,
+            parent: Some(parent.id),
+            ..Task::new("")
+        })?;
+        self.show_new_task_input = true;
+        Ok(())
+    }
+
+    /// Commits the name typed into an unlocked task row; an empty name deletes the task.
+    /// `#tag` words are added to the task's tags rather than kept in the name.
+    fn submit_task_name(&mut self, index: usize) -> Result<()> {
+        let task = &self.tasks[index];
+        if task.just_created {
+            self.show_new_task_input = false;
+        }
+        let id = task.id;
+        let (name, typed_tags) = split_tags(&task.name);
+        let mut tags = task.tags.clone();
+        tags.extend(typed_tags);
+        tags.sort();
+        tags.dedup();
+        let tags_changed = tags != task.tags;
+        self.store.set_task_locked(true, id)?;
+        self.store.set_task_just_created(true, id)?;
+        // One edit, so a single undo takes back the name and its tags.
+        self.store.batch(&[], |store| {
+            store.set_task_name(name.clone(), id)?;
+            if tags_changed {
+                store.set_task_tags(tags, id)?;
+            }
+            if name.is_empty() {
+                store.delete_task(id)?;
+            }
+            Ok(())
+        })?;
+        if name.is_empty() && self.current_task == Some(id) {
+            self.current_task = None;
+        }
+        Ok(())
+    }
+
+    fn undo(&mut self) {
+        let result = self.store.undo();
+        if let Some(Some(label)) = self.errors.report("Could not undo", result) {
+            self.undo_toast = Some((format!("Undone: {}", label), now(), false));
+        }
+        self.reload();
+        self.reload_done();
+    }
+
+    fn redo(&mut self) {
+        let result = self.store.redo();
+        if let Some(Some(label)) = self.errors.report("Could not redo", result) {
+            self.undo_toast = Some((format!("Redone: {}", label), now(), false));
         }
+        self.reload();
+        self.reload_done();
     }
-    tx.commit().unwrap();
 }
 
-fn delete_pomodoros_without_task(conn: &mut Connection) {
-    let tx = conn.transaction().unwrap();
-    tx.execute("DELETE from timers where task is NULL", [])
-        .unwrap();
-    tx.commit().unwrap();
+fn play_notificaiton() -> Result<()> {
+    let (_stream, stream_handle) = OutputStream::try_default()?;
+    let file = BufReader::new(open_file("assets/notification.mp3")?);
+    let source = Decoder::new(file)?;
+    stream_handle.play_raw(source.convert_samples())?;
+    std::thread::sleep(std::time::Duration::from_millis(1000));
+    Ok(())
 }
 
-fn create_task(conn: &mut Connection, task: Task) {
-    let tx = conn.transaction().unwrap();
-    tx.execute(
-        "INSERT INTO tasks (name, done, estimate, locked, just_created) VALUES (?1, ?2, ?3, ?4, ?5)",
-        (
-            task.name,
-            task.done,
-            task.estimate,
-            task.locked,
-            task.just_created,
-        ),
-    )
-    .unwrap();
-    tx.commit().unwrap();
+/// `today`, `tomorrow`, `yesterday` or the date itself.
+fn day_label(day: Date, today: Date) -> String {
+    match (day - today).whole_days() {
+        0 => "today".to_string(),
+        1 => "tomorrow".to_string(),
+        -1 => "yesterday".to_string(),
+        _ => day.to_string(),
+    }
 }
 
-fn add_pomodoros(conn: &mut Connection, amount: i32, date: OffsetDateTime) {
-    for n in 1..=amount {
-        create_timer(
-            conn,
-            Timer {
-                id: 0,
-                is_pomodoro: true,
-                start: date,
-                duration: 25,
-                task: Some(1),
-            },
+/// The text of `input` for `menu` of task `id`, starting out empty when it
+/// was last typed into another menu.
+fn day_input<'a>(
+    input: &'a mut (i32, &'static str, String),
+    id: i32,
+    menu: &'static str,
+) -> &'a mut String {
+    if (input.0, input.1) != (id, menu) {
+        *input = (id, menu, String::new());
+    }
+    &mut input.2
+}
+
+/// Choices for a task's day. Returns the picked day, `Some(None)` to clear it.
+fn day_menu(ui: &mut egui::Ui, input: &mut String, today: Date) -> Option<Option<Date>> {
+    let mut picked = None;
+    if ui.button("Today").clicked() {
+        picked = Some(Some(today));
+    }
+    if ui.button("Tomorrow").clicked() {
+        picked = Some(today.next_day());
+    }
+    ui.horizontal(|ui| {
+        let invalid = !input.trim().is_empty() && parse_date(input).is_none();
+        let response = ui.add(
+            egui::TextEdit::singleline(input)
+                .hint_text("YYYY-MM-DD")
+                .desired_width(90.0)
+                .text_color_opt(invalid.then_some(Color32::RED)),
         );
+        let entered = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+        if (ui.button("Set").clicked() || entered) && !invalid {
+            if let Some(day) = parse_date(input) {
+                picked = Some(Some(day));
+            }
+        }
+    });
+    if ui.button("Clear").clicked() {
+        picked = Some(None);
     }
+    if picked.is_some() {
+        input.clear();
+        ui.close_menu();
+    }
+    picked
 }
 
-fn get_task_pomodoros(conn: &mut Connection, task_id: i32) -> usize {
-    let mut timers: Vec<Timer> = vec![];
-    let mut stmt = conn
-        .prepare("SELECT * FROM timers where task = :id")
-        .unwrap();
-    let timers_iter = stmt
-        .query_map(&[(":id", &task_id)], |row| {
-            Ok(Timer {
-                id: row.get(0)?,
-                is_pomodoro: row.get(1)?,
-                start: row.get(2)?,
-                duration: row.get(3)?,
-                task: row.get(4)?,
-            })
+/// Choices for how a task repeats. Returns the picked rule, `Some(None)` to
+/// stop repeating.
+fn repeat_menu(
+    ui: &mut egui::Ui,
+    current: &Option<Recurrence>,
+    every_days: &mut u32,
+) -> Option<Option<Recurrence>> {
+    let mut picked = None;
+    for (label, recurrence) in [
+        ("Daily", Recurrence::Daily),
+        ("Weekdays", Recurrence::Weekdays),
+    ] {
+        if ui
+            .selectable_label(current.as_ref() == Some(&recurrence), label)
+            .clicked()
+        {
+            picked = Some(Some(recurrence));
+        }
+    }
+    ui.horizontal(|ui| {
+        ui.label("Weekly on");
+        let days = match current {
+            Some(Recurrence::Weekly(days)) => days.clone(),
+            _ => vec![],
+        };
+        for day in [
+            Weekday::Monday,
+            Weekday::Tuesday,
+            Weekday::Wednesday,
+            Weekday::Thursday,
+            Weekday::Friday,
+            Weekday::Saturday,
+            Weekday::Sunday,
+        ] {
+            let selected = days.contains(&day);
+            if ui
+                .selectable_label(selected, &day.to_string()[..2])
+                .clicked()
+            {
+                let mut days = days.clone();
+                if selected {
+                    days.retain(|other| *other != day);
+                } else {
+                    days.push(day);
+                }
+                picked = Some(Recurrence::weekly(&days));
+            }
+        }
+    });
+    ui.horizontal(|ui| {
+        ui.label("Every");
+        ui.add(egui::DragValue::new(every_days).range(1..=365));
+        ui.label("days");
+        if ui.button("Set").clicked() {
+            picked = Some(Some(Recurrence::EveryDays(*every_days)));
+        }
+    });
+    if ui.button("Don't repeat").clicked() {
+        picked = Some(None);
+    }
+    picked
+}
+
+/// The notes panel of `task`: rendered Markdown, or an editor while
+/// `draft` holds its text. Returns whether the notes were saved.
+fn show_notes(
+    ui: &mut egui::Ui,
+    task: &Task,
+    draft: &mut Option<(i32, String)>,
+    store: &mut History,
+    errors: &mut ErrorBanner,
+) -> bool {
+    let mut saved = false;
+    match draft {
+        Some((id, text)) if *id == task.id => {
+            ui.add(
+                egui::TextEdit::multiline(text)
+                    .hint_text("Notes, in Markdown")
+                    .desired_rows(4),
+            );
+            let (save, cancel) = ui
+                .horizontal(|ui| (ui.button("Save").clicked(), ui.button("Cancel").clicked()))
+                .inner;
+            if save {
+                errors.report(
+                    "Could not save notes",
+                    store.set_task_notes(text.trim_end().to_string(), task.id),
+                );
+                saved = true;
+            }
+            if save || cancel {
+                *draft = None;
+            }
+        }
+        _ => {
+            if task.notes.is_empty() {
+                ui.label(RichText::new("No notes yet").weak());
+            } else {
+                markdown::show(ui, &task.notes);
+            }
+            if ui.button("Edit").clicked() {
+                *draft = Some((task.id, task.notes.clone()));
+            }
+        }
+    }
+    saved
+}
+
+/// One line per interruption: its mark, time and note.
+fn interruption_list(interruptions: &[Interruption]) -> String {
+    let lines: Vec<String> = interruptions
+        .iter()
+        .map(|interruption| {
+            format!(
+                "{} {:0>2}:{:0>2} {}",
+                interruption.kind.mark(),
+                interruption.at.hour(),
+                interruption.at.minute(),
+                interruption.note
+            )
+            .trim_end()
+            .to_string()
         })
-        .unwrap();
-    for timer in timers_iter {
-        timers.push(timer.unwrap());
+        .collect();
+    lines.join("\n")
+}
+
+/// Red for P1, fading towards P3.
+fn priority_color(priority: u8) -> Color32 {
+    match priority {
+        1 => Color32::from_hex("#D32F2F").unwrap(),
+        2 => Color32::from_hex("#F57C00").unwrap(),
+        _ => Color32::from_hex("#1976D2").unwrap(),
     }
-    timers.len()
 }
 
-fn is_timer_over(timer: &Timer) -> bool {
-    let start = timer.start;
-    let duration = timer.duration;
-    let now = OffsetDateTime::now_local().unwrap();
-    let end = start
-        .checked_add(Duration::minutes(duration.into()))
-        .unwrap();
-    let difference = end - now;
-    let seconds = difference.whole_seconds() % 60;
-    let minutes = (difference.whole_seconds() / 60) % 60;
-    (seconds < 0 || minutes < 0) && timer.is_pomodoro
+/// Color of a tag, derived from its name so it stays the same everywhere.
+fn tag_color(tag: &str) -> Color32 {
+    // FNV-1a, stable across runs unlike the std hasher.
+    let hash = tag.bytes().fold(0x811c9dc5u32, |hash, byte| {
+        (hash ^ u32::from(byte)).wrapping_mul(0x01000193)
+    });
+    egui::ecolor::Hsva::new((hash % 360) as f32 / 360.0, 0.45, 0.9, 1.0).into()
 }
 
-fn update_timer_task(conn: &mut Connection, timer_id: i32, task_id: i32) {
-    let tx = conn.transaction().unwrap();
-    tx.execute(
-        "UPDATE timers SET task = ?1 where id = ?2",
-        (task_id, timer_id),
+/// `#tag` on its color, faded unless `selected`.
+fn tag_chip(ui: &mut egui::Ui, tag: &str, selected: bool) -> egui::Response {
+    let fill = if selected {
+        tag_color(tag)
+    } else {
+        tag_color(tag).gamma_multiply(0.35)
+    };
+    ui.add(
+        Button::new(
+            RichText::new(format!("#{}", tag))
+                .small()
+                .color(Color32::BLACK),
+        )
+        .fill(fill)
+        .small(),
     )
-    .unwrap();
-    tx.commit().unwrap();
+    .on_hover_cursor(egui::CursorIcon::PointingHand)
 }
 
-impl eframe::App for MyApp {
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        egui::CentralPanel::default().show(ctx, |ui| {
-            ctx.set_pixels_per_point(2.0);
-            let mut update_ui = false;
-            let timers = get_running_timers(&mut self.conn);
-            if self.timers_queue.len() > 0 && timers.len() == 0 {
-                match self.timers_queue.pop() {
-                    Some(mut timer) => {
-                        timer.start = OffsetDateTime::now_local().unwrap();
-                        create_timer(&mut self.conn, timer);
-                        ctx.send_viewport_cmd(egui::ViewportCommand::Title(
-                            DEFAULT_WINDOW_TITLE.to_string(),
-                        ));
-                        self.played_notification = false;
-                    }
-                    None => {}
+fn is_timer_over(timer: &Timer) -> bool {
+    timer.remaining(now()).whole_seconds() < 0 && timer.is_pomodoro()
+}
+
+impl MyApp {
+    fn show_menu(&mut self, ctx: &egui::Context) {
+        egui::TopBottomPanel::top("menu").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.selectable_value(&mut self.view, View::Tasks, "Tasks");
+                ui.selectable_value(&mut self.view, View::Today, "Today");
+                if ui
+                    .selectable_value(&mut self.view, View::Done, "Done")
+                    .clicked()
+                {
+                    self.reload_done();
                 }
-            }
-            for task in self.tasks.iter_mut() {
-                if task.locked {
-                    ui.horizontal(|ui| {
-                        if ui.checkbox(&mut task.done, "").changed() {
-                            set_task_status(&mut self.conn, task.done, task.id);
-                            update_ui = true;
-                        };
-                        if ui.label(task.name.clone()).double_clicked() {
-                            set_task_locked(&mut self.conn, false, task.id);
-                            update_ui = true;
-                        }
-                        if timers.len() > 0 && is_timer_over(&timers[0]) {
-                            if ui
-                                .button("+")
-                                .on_hover_cursor(egui::CursorIcon::PointingHand)
-                                .clicked()
-                            {
-                                update_timer_task(&mut self.conn, timers[0].id, task.id);
-                                update_ui = true;
-                                ctx.send_viewport_cmd(egui::ViewportCommand::Title(
-                                    DEFAULT_WINDOW_TITLE.to_string(),
-                                ));
-                            }
-                        }
-                        let pomodoros = get_task_pomodoros(&mut self.conn, task.id);
-                        for n in 1..=pomodoros {
-                            ui.image(egui::include_image!("../assets/pomodoro.png"));
-                        }
-                        if task.estimate > pomodoros.try_into().unwrap() {
-                            for n in 1..=task.estimate - pomodoros as i32 {
-                                if ui
-                                    .add(ImageButton::frame(
-                                        ImageButton::new(egui::include_image!(
-                                            "../assets/estimation.png"
-                                        )),
-                                        false,
-                                    ))
-                                    .on_hover_cursor(egui::CursorIcon::PointingHand)
-                                    .clicked()
-                                {
-                                    set_task_estimate(&mut self.conn, task.estimate - 1, task.id);
-                                    update_ui = true;
-                                }
+                if ui
+                    .selectable_value(&mut self.view, View::Statistics, "Statistics")
+                    .clicked()
+                {
+                    self.reload_statistics();
+                }
+                ui.menu_button("Export", |ui| {
+                    for format in Format::ALL {
+                        if ui.button(format.label()).clicked() {
+                            let path = format!("export.{}", format.extension());
+                            let result = export_to_file(&self.store, format, &path);
+                            if self.errors.report("Could not export", result).is_some() {
+                                self.exported_to = Some(path);
                             }
+                            ui.close_menu();
                         }
+                    }
+                });
+                if ui.button("Import").clicked() {
+                    self.import = Some(ImportDialog::default());
+                }
+                if let Some(path) = &self.exported_to {
+                    ui.label(format!("Exported to {}", path));
+                }
+            });
+        });
+    }
 
+    fn show_tasks(&mut self, ui: &mut egui::Ui, timers: &[Timer]) {
+        let mut update_ui = false;
+        let mut submitted_task = None;
+        let mut add_subtask_to = None;
+        let mut dropped = None;
+        let (move_up, move_down, reprioritise) = ui.input_mut(|i| {
+            (
+                i.consume_key(egui::Modifiers::ALT, egui::Key::ArrowUp),
+                i.consume_key(egui::Modifiers::ALT, egui::Key::ArrowDown),
+                i.consume_key(egui::Modifiers::ALT, egui::Key::P),
+            )
+        });
+        if move_up || move_down {
+            let result = self.move_selected_task(move_up);
+            self.errors.report("Could not move task", result);
+            update_ui = true;
+        }
+        if reprioritise {
+            let result = self.cycle_selected_priority();
+            self.errors.report("Could not update task", result);
+            update_ui = true;
+        }
+        self.show_tag_filter(ui);
+        let today = now().date();
+        let today_view = self.view == View::Today;
+        // A focus timer started elsewhere, like the CLI, has its own task.
+        let focus = timers.iter().find(|timer| timer.is_pomodoro());
+        let current_task = focus.map_or(self.current_task, |timer| timer.task);
+        // The Today view is a flat list across all projects.
+        let collapsed = if today_view {
+            HashSet::new()
+        } else {
+            self.collapsed.clone()
+        };
+        for (index, depth) in task_tree(&self.tasks, &collapsed) {
+            let id = self.tasks[index].id;
+            let has_subtasks = !today_view && self.tasks.iter().any(|task| task.parent == Some(id));
+            if !self.is_listed(&self.tasks[index], today) {
+                continue;
+            }
+            let task = &mut self.tasks[index];
+            let indent = if today_view { 0.0 } else { depth as f32 * 16.0 };
+            if task.locked {
+                let row = ui.horizontal(|ui| {
+                    ui.add_space(indent);
+                    ui.dnd_drag_source(egui::Id::new(("task", task.id)), task.id, |ui| {
+                        ui.label("☰");
+                    })
+                    .response
+                    .on_hover_cursor(egui::CursorIcon::Grab);
+                    if has_subtasks {
+                        let collapsed = self.collapsed.contains(&task.id);
                         if ui
-                            .add(ImageButton::frame(
-                                ImageButton::new(egui::include_image!(
-                                    "../assets/add_estimation.png"
-                                )),
+                            .add(egui::Button::frame(
+                                egui::Button::new(if collapsed { "▶" } else { "▼" }),
                                 false,
                             ))
                             .on_hover_cursor(egui::CursorIcon::PointingHand)
                             .clicked()
                         {
-                            let new_estimation =
-                                max(pomodoros, task.estimate.try_into().unwrap()) + 1;
-                            set_task_estimate(
-                                &mut self.conn,
-                                new_estimation.try_into().unwrap(),
-                                task.id,
-                            );
-                            update_ui = true;
+                            if collapsed {
+                                self.collapsed.remove(&task.id);
+                            } else {
+                                self.collapsed.insert(task.id);
+                            }
                         }
-                    });
-                } else {
-                    let response = ui
-                        .add(egui::TextEdit::singleline(&mut task.name).hint_text("Task name..."));
-                    if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
-                        set_task_locked(&mut self.conn, true, task.id);
-                        set_task_just_created(&mut self.conn, true, task.id);
-                        set_task_name(&mut self.conn, task.name.clone(), task.id);
-                        if task.name.is_empty() {
-                            delete_task(&mut self.conn, task.id);
+                    }
+                    if ui.checkbox(&mut task.done, "").changed() {
+                        let (done, id) = (task.done, task.id);
+                        self.errors.report(
+                            "Could not update task",
+                            self.store.set_task_status(done, id),
+                        );
+                        if done && self.current_task == Some(id) {
+                            self.current_task = None;
                         }
                         update_ui = true;
-                        if task.just_created {
-                            self.show_new_task_input = false;
+                    };
+                    let is_current = current_task == Some(task.id);
+                    let current_icon = if is_current {
+                        RichText::new("▶").color(Color32::from_hex("#A80000").unwrap())
+                    } else {
+                        RichText::new("▶").weak()
+                    };
+                    if ui
+                        .add(egui::Button::frame(egui::Button::new(current_icon), false))
+                        .on_hover_text(if is_current {
+                            "Stop working on this task"
+                        } else {
+                            "Work on this task, focus timers are credited to it"
+                        })
+                        .on_hover_cursor(egui::CursorIcon::PointingHand)
+                        .clicked()
+                    {
+                        let current = if is_current { None } else { Some(task.id) };
+                        self.current_task = current;
+                        if let Some(timer) = focus {
+                            self.errors.report(
+                                "Could not set current task",
+                                self.store.set_timer_task(timer.id, current),
+                            );
                         }
+                        update_ui = true;
                     }
-                    if task.just_created {
-                        response.request_focus();
+                    if task.priority < DEFAULT_PRIORITY {
+                        ui.label(
+                            RichText::new(format!("P{}", task.priority))
+                                .small()
+                                .strong()
+                                .color(priority_color(task.priority)),
+                        )
+                        .on_hover_text("Priority, Alt+P on the selected task changes it");
                     }
-                }
-            }
-            if !self.show_new_task_input {
-                if ui
-                    .add(egui::Button::frame(egui::Button::new("+ Add Task"), false))
-                    .on_hover_cursor(egui::CursorIcon::PointingHand)
-                    .clicked()
-                {
-                    create_task(
-                        &mut self.conn,
-                        Task {
-                            id: 0,
-                            name: self.new_task_name.clone(),
-                            done: false,
-                            locked: false,
-                            estimate: 0,
-                            just_created: true,
-                        },
-                    );
-                    self.new_task_name = "".to_string();
-                    self.show_new_task_input = true;
-                    update_ui = true;
-                }
-            }
-            ui.with_layout(egui::Layout::bottom_up(egui::Align::Center), |ui| {
-                ui.horizontal(|ui| {
-                    ui.scope(|ui| {
-                        ui.style_mut().visuals.widgets.hovered.weak_bg_fill =
-                            Color32::from_hex("#A80000").unwrap();
-                        let focus_button: Button;
-                        if timers.len() > 0 && timers[0].is_pomodoro {
-                            focus_button = Button::fill(
-                                Button::new(
-                                    RichText::new(format!("Focus x{}", self.pomodoros_estimate))
-                                        .color(Color32::from_hex("#FFF9F0").unwrap()),
-                                ),
-                                Color32::from_hex("#A80000").unwrap(),
-                            );
+                    let selected = self.selected_task == Some(task.id);
+                    let overdue = task.is_overdue(today);
+                    let mut name_text = RichText::new(task.name.clone());
+                    if overdue {
+                        name_text = name_text.color(Color32::RED);
+                    }
+                    if is_current && focus.is_some() {
+                        let focus_fill = Color32::from_rgba_unmultiplied(0xA8, 0x00, 0x00, 0x30);
+                        name_text = name_text.strong().background_color(focus_fill);
+                    }
+                    let name = ui.selectable_label(selected, name_text);
+                    if name.clicked() {
+                        self.selected_task = if selected { None } else { Some(task.id) };
+                    }
+                    if let Some(due) = task.due {
+                        let label = RichText::new(format!("due {}", day_label(due, today)));
+                        ui.label(if overdue {
+                            label.color(Color32::RED)
                         } else {
-                            focus_button =
-                                Button::new(format!("Focus x{}", self.pomodoros_estimate));
-                        }
-                        if ui
-                            .add(focus_button)
-                            .on_hover_cursor(egui::CursorIcon::PointingHand)
-                            .clicked()
-                        {
-                            self.timers_queue.push(Timer {
-                                id: 0,
-                                is_pomodoro: true,
-                                start: OffsetDateTime::now_local().unwrap(),
-                                duration: self.configuration.focus_duration,
-                                task: None,
-                            });
+                            label.weak()
+                        });
+                    }
+                    let mut removed_tag = None;
+                    for tag in task.tags.iter() {
+                        let chip = tag_chip(ui, tag, true)
+                            .on_hover_text("Click to filter by this tag, right-click to remove it");
+                        if chip.clicked() {
+                            self.tag_filter.insert(tag.clone());
                         }
-                    });
-                    ui.scope(|ui| {
-                        ui.style_mut().visuals.widgets.hovered.weak_bg_fill =
-                            Color32::from_hex("#005C00").unwrap();
-                        let short_break_button: Button;
-                        if timers.len() > 0
-                            && !timers[0].is_pomodoro
-                            && timers[0].duration == self.configuration.short_break_duration
-                        {
-                            short_break_button = Button::fill(
-                                Button::new(
-                                    RichText::new("Short Break")
-                                        .color(Color32::from_hex("#FFF9F0").unwrap()),
-                                ),
-                                Color32::from_hex("#005C00").unwrap(),
-                            );
+                        chip.context_menu(|ui| {
+                            if ui.button("Remove tag").clicked() {
+                                removed_tag = Some(tag.clone());
+                                ui.close_menu();
+                            }
+                        });
+                    }
+                    if let Some(removed) = removed_tag {
+                        let tags = task.tags.iter().filter(|tag| **tag != removed).cloned();
+                        self.errors.report(
+                            "Could not update task",
+                            self.store.set_task_tags(tags.collect(), task.id),
+                        );
+                        update_ui = true;
+                    }
+                    if let Some(recurrence) = &task.recurrence {
+                        ui.label("🔁")
+                            .on_hover_text(format!("Repeats {}", recurrence.label()));
+                    }
+                    let notes_open = self.open_notes.contains(&task.id);
+                    let notes_icon = if task.notes.is_empty() && !notes_open {
+                        RichText::new("📝").weak()
+                    } else {
+                        RichText::new("📝")
+                    };
+                    if ui
+                        .add(egui::Button::frame(egui::Button::new(notes_icon), false))
+                        .on_hover_text(if notes_open {
+                            "Hide notes"
+                        } else {
+                            "Show notes"
+                        })
+                        .on_hover_cursor(egui::CursorIcon::PointingHand)
+                        .clicked()
+                    {
+                        if notes_open {
+                            self.open_notes.remove(&task.id);
                         } else {
-                            short_break_button = Button::new("Short Break");
+                            self.open_notes.insert(task.id);
                         }
-                        if ui
-                            .add(short_break_button)
+                    }
+                    if let Some(planned) = task.planned.filter(|_| !today_view) {
+                        ui.label(
+                            RichText::new(format!("planned {}", day_label(planned, today))).weak(),
+                        );
+                    }
+                    if name.double_clicked() {
+                        self.errors.report(
+                            "Could not update task",
+                            self.store.set_task_locked(false, task.id),
+                        );
+                        update_ui = true;
+                    }
+                    if !timers.is_empty()
+                        && is_timer_over(&timers[0])
+                        && ui
+                            .button("+")
                             .on_hover_cursor(egui::CursorIcon::PointingHand)
                             .clicked()
-                        {
-                            self.timers_queue.push(Timer {
-                                id: -1,
-                                is_pomodoro: false,
-                                start: OffsetDateTime::now_local().unwrap(),
-                                duration: self.configuration.short_break_duration,
-                                task: None,
-                            });
+                    {
+                        self.errors.report(
+                            "Could not add pomodoro to task",
+                            self.store.update_timer_task(timers[0].id, task.id),
+                        );
+                        update_ui = true;
+                        ui.ctx().send_viewport_cmd(egui::ViewportCommand::Title(
+                            DEFAULT_WINDOW_TITLE.to_string(),
+                        ));
+                    }
+                    let pomodoros = self.task_pomodoros.get(&task.id).copied();
+                    let pomodoros = pomodoros.unwrap_or_default();
+                    // Both include the subtasks; buttons only change the task's own estimate.
+                    let estimate = self
+                        .task_estimates
+                        .get(&task.id)
+                        .copied()
+                        .unwrap_or(task.estimate);
+                    for _ in 1..=pomodoros {
+                        ui.image(egui::include_image!("../assets/pomodoro.png"));
+                    }
+                    if let Some(interruptions) = self
+                        .task_interruptions
+                        .get(&task.id)
+                        .filter(|interruptions| !interruptions.is_empty())
+                    {
+                        ui.label(RichText::new(interruption_marks(interruptions)).strong())
+                            .on_hover_text(interruption_list(interruptions));
+                    }
+                    if estimate > pomodoros.try_into().unwrap() {
+                        for _ in 1..=estimate - pomodoros as i32 {
+                            if ui
+                                .add(ImageButton::frame(
+                                    ImageButton::new(egui::include_image!(
+                                        "../assets/estimation.png"
+                                    )),
+                                    false,
+                                ))
+                                .on_hover_cursor(egui::CursorIcon::PointingHand)
+                                .clicked()
+                                && task.estimate > 0
+                            {
+                                self.errors.report(
+                                    "Could not update estimate",
+                                    self.store.set_task_estimate(task.estimate - 1, task.id),
+                                );
+                                update_ui = true;
+                            }
                         }
-                    });
-                    ui.scope(|ui| {
-                        ui.style_mut().visuals.widgets.hovered.weak_bg_fill =
-                            Color32::from_hex("#1F1FFF").unwrap();
-                        let long_break_button: Button;
-                        if timers.len() > 0
-                            && !timers[0].is_pomodoro
-                            && timers[0].duration == self.configuration.long_break_duration
+                    }
+
+                    if ui
+                        .add(ImageButton::frame(
+                            ImageButton::new(egui::include_image!("../assets/add_estimation.png")),
+                            false,
+                        ))
+                        .on_hover_cursor(egui::CursorIcon::PointingHand)
+                        .clicked()
+                    {
+                        let new_estimation = max(pomodoros, estimate.try_into().unwrap()) + 1;
+                        let own_estimation = task.estimate + new_estimation as i32 - estimate;
+                        self.errors.report(
+                            "Could not update estimate",
+                            self.store.set_task_estimate(own_estimation, task.id),
+                        );
+                        update_ui = true;
+                    }
+                    if let Some(suggested) = self.suggested_estimate {
+                        if estimate == 0
+                            && pomodoros == 0
+                            && ui
+                                .add(egui::Button::frame(
+                                    egui::Button::new(
+                                        RichText::new(format!("~{}", suggested)).weak(),
+                                    ),
+                                    false,
+                                ))
+                                .on_hover_text("Suggested estimate, based on finished tasks")
+                                .on_hover_cursor(egui::CursorIcon::PointingHand)
+                                .clicked()
                         {
-                            long_break_button = Button::fill(
-                                Button::new(
-                                    RichText::new("Long Break")
-                                        .color(Color32::from_hex("#FFF9F0").unwrap()),
-                                ),
-                                Color32::from_hex("#1F1FFF").unwrap(),
+                            self.errors.report(
+                                "Could not update estimate",
+                                self.store.set_task_estimate(suggested, task.id),
                             );
-                        } else {
-                            long_break_button = Button::new("Long Break");
+                            update_ui = true;
                         }
-                        if ui
-                            .add(long_break_button)
-                            .on_hover_cursor(egui::CursorIcon::PointingHand)
-                            .clicked()
-                        {
-                            self.timers_queue.push(Timer {
-                                id: 0,
-                                is_pomodoro: false,
-                                start: OffsetDateTime::now_local().unwrap(),
-                                duration: self.configuration.long_break_duration,
-                                task: None,
-                            });
+                    }
+                    ui.menu_button("...", |ui| {
+                        if ui.button("Add subtask").clicked() {
+                            add_subtask_to = Some(index);
+                            ui.close_menu();
                         }
-                    });
-                    if timers.len() > 0 {
-                        let timer = &timers[0];
-                        let start = timer.start;
-                        let duration = timer.duration;
-                        let now = OffsetDateTime::now_local().unwrap();
-                        if (now - self.last_checked_time).whole_milliseconds() >= 300 {
-                            let end = start
-                                .checked_add(Duration::minutes(duration.into()))
-                                .unwrap();
-                            let difference = end - now;
-                            let seconds = difference.whole_seconds() % 60;
-                            let minutes = (difference.whole_seconds() / 60) % 60;
-                            if difference.whole_seconds() <= 0 {
-                                if timer.is_pomodoro {
-                                    self.timer_value = "Done! Add point to task.".to_string();
-                                } else {
-                                    delete_pomodoros_without_task(&mut self.conn);
-                                }
-                                if !self.played_notification {
-                                    play_notificaiton();
-                                    self.played_notification = true;
-                                    ctx.send_viewport_cmd(egui::ViewportCommand::Title(
-                                        DEFAULT_WINDOW_TITLE.to_string(),
-                                    ));
+                        ui.menu_button("Due", |ui| {
+                            let input = day_input(&mut self.day_input, task.id, "Due");
+                            if let Some(due) = day_menu(ui, input, today) {
+                                self.errors.report(
+                                    "Could not update task",
+                                    self.store.set_task_due(due, task.id),
+                                );
+                                update_ui = true;
+                            }
+                        });
+                        ui.menu_button("Plan for", |ui| {
+                            let input = day_input(&mut self.day_input, task.id, "Plan for");
+                            if let Some(planned) = day_menu(ui, input, today) {
+                                self.errors.report(
+                                    "Could not update task",
+                                    self.store.set_task_planned(planned, task.id),
+                                );
+                                update_ui = true;
+                            }
+                        });
+                        ui.menu_button("Priority", |ui| {
+                            for priority in 1..=DEFAULT_PRIORITY {
+                                if ui
+                                    .selectable_label(
+                                        task.priority == priority,
+                                        format!("P{}", priority),
+                                    )
+                                    .clicked()
+                                {
+                                    self.errors.report(
+                                        "Could not update task",
+                                        self.store.set_task_priority(priority, task.id),
+                                    );
+                                    update_ui = true;
+                                    ui.close_menu();
                                 }
-                            } else {
-                                self.timer_value = format!("{:0>2}:{:0>2}", minutes, seconds);
-                                if timer.is_pomodoro {
-                                    ctx.send_viewport_cmd(egui::ViewportCommand::Title(format!(
-                                        "{:0>2}:{:0>2} Focus",
-                                        minutes, seconds
-                                    )));
-                                } else {
-                                    ctx.send_viewport_cmd(egui::ViewportCommand::Title(format!(
-                                        "{:0>2}:{:0>2}",
-                                        minutes, seconds
-                                    )));
+                            }
+                        });
+                        ui.menu_button("Repeat", |ui| {
+                            if let Some(recurrence) =
+                                repeat_menu(ui, &task.recurrence, &mut self.repeat_days)
+                            {
+                                self.errors.report(
+                                    "Could not update task",
+                                    self.store.set_task_recurrence(recurrence, task.id),
+                                );
+                                update_ui = true;
+                            }
+                        });
+                        ui.menu_button("Move to", |ui| {
+                            let mut destinations = vec![(None, "Inbox")];
+                            for project in self.projects.iter() {
+                                destinations.push((Some(project.id), project.name.as_str()));
+                            }
+                            for (project, name) in destinations {
+                                if ui
+                                    .add_enabled(project != task.project, Button::new(name))
+                                    .clicked()
+                                {
+                                    self.errors.report(
+                                        "Could not move task",
+                                        self.store.set_task_project(project, task.id),
+                                    );
+                                    update_ui = true;
+                                    ui.close_menu();
                                 }
                             }
-                            self.last_checked_time = now;
-                        }
-                        ui.label(self.timer_value.clone());
-                        ui.ctx()
-                            .request_repaint_after(std::time::Duration::from_millis(300));
-                        if ui
-                            .add(egui::Button::frame(egui::Button::new("x"), false))
-                            .on_hover_cursor(egui::CursorIcon::PointingHand)
-                            .clicked()
-                        {
-                            delete_pomodoros_without_task(&mut self.conn);
-                            ctx.send_viewport_cmd(egui::ViewportCommand::Title(
+                        });
+                    });
+                });
+                if let Some(id) = row.response.dnd_release_payload::<i32>() {
+                    dropped = Some((*id, task.id));
+                }
+                if self.open_notes.contains(&task.id) {
+                    ui.horizontal(|ui| {
+                        ui.add_space(indent + 24.0);
+                        ui.group(|ui| {
+                            ui.vertical(|ui| {
+                                update_ui |= show_notes(
+                                    ui,
+                                    task,
+                                    &mut self.notes_draft,
+                                    &mut self.store,
+                                    &mut self.errors,
+                                );
+                            });
+                        });
+                    });
+                }
+            } else {
+                ui.horizontal(|ui| {
+                    ui.add_space(indent);
+                    let response = ui.add(
+                        egui::TextEdit::singleline(&mut task.name).hint_text("Task name... #tags"),
+                    );
+                    if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                        submitted_task = Some(index);
+                    }
+                    if task.just_created {
+                        response.request_focus();
+                    }
+                });
+            }
+        }
+        if let Some((id, target)) = dropped {
+            let result = self.move_task(id, target);
+            self.errors.report("Could not move task", result);
+            update_ui = true;
+        }
+        if let Some(index) = add_subtask_to {
+            let result = self.add_subtask(index);
+            self.errors.report("Could not create task", result);
+            update_ui = true;
+        }
+        if let Some(index) = submitted_task {
+            let result = self.submit_task_name(index);
+            self.errors.report("Could not save task", result);
+            update_ui = true;
+        }
+        if !self.show_new_task_input
+            && ui
+                .add(egui::Button::frame(egui::Button::new("+ Add Task"), false))
+                .on_hover_cursor(egui::CursorIcon::PointingHand)
+                .clicked()
+        {
+            let result = self.add_task();
+            self.errors.report("Could not create task", result);
+            update_ui = true;
+        }
+        if update_ui {
+            self.reload();
+        }
+    }
+
+    /// Chips for the tags of open tasks; picked ones narrow down the list.
+    fn show_tag_filter(&mut self, ui: &mut egui::Ui) {
+        let tags: BTreeSet<&String> = self.tasks.iter().flat_map(|task| &task.tags).collect();
+        if tags.is_empty() && self.tag_filter.is_empty() {
+            return;
+        }
+        let mut toggled = None;
+        ui.horizontal_wrapped(|ui| {
+            ui.label("Tags");
+            for tag in tags {
+                if tag_chip(ui, tag, self.tag_filter.contains(tag)).clicked() {
+                    toggled = Some(tag.clone());
+                }
+            }
+            if !self.tag_filter.is_empty() && ui.button("Show all").clicked() {
+                self.tag_filter.clear();
+            }
+        });
+        if let Some(tag) = toggled {
+            if !self.tag_filter.remove(&tag) {
+                self.tag_filter.insert(tag);
+            }
+        }
+        ui.separator();
+    }
+
+    fn show_done(&mut self, ui: &mut egui::Ui) {
+        let mut update_ui = false;
+        ui.horizontal(|ui| {
+            for (label, date) in [("From", &mut self.done_from), ("To", &mut self.done_to)] {
+                ui.label(label);
+                let invalid = !date.trim().is_empty() && parse_date(date).is_none();
+                let response = ui.add(
+                    egui::TextEdit::singleline(date)
+                        .hint_text("YYYY-MM-DD")
+                        .desired_width(90.0)
+                        .text_color_opt(invalid.then_some(Color32::RED)),
+                );
+                update_ui |= response.changed();
+            }
+            let today = now().date();
+            for (label, days) in [
+                ("Last 7 days", Some(7)),
+                ("Last 30 days", Some(30)),
+                ("All", None),
+            ] {
+                if ui.button(label).clicked() {
+                    self.done_from = days
+                        .map(|days| (today - Duration::days(days - 1)).to_string())
+                        .unwrap_or_default();
+                    self.done_to = "".to_string();
+                    update_ui = true;
+                }
+            }
+        });
+        ui.separator();
+        let mut reopened = None;
+        let mut total_pomodoros = 0;
+        egui::Grid::new("done_tasks").striped(true).show(ui, |ui| {
+            for task in self.done_tasks.iter() {
+                let mut done = true;
+                if ui
+                    .checkbox(&mut done, "")
+                    .on_hover_text("Untick to move the task back to the list")
+                    .changed()
+                {
+                    reopened = Some(task.id);
+                }
+                ui.label(task.name.clone());
+                ui.label(
+                    task.completed_at
+                        .and_then(|completed_at| {
+                            completed_at
+                                .format(format_description!("[year]-[month]-[day] [hour]:[minute]"))
+                                .ok()
+                        })
+                        .unwrap_or_else(|| "-".to_string()),
+                );
+                let pomodoros = self.task_pomodoros.get(&task.id).copied();
+                let pomodoros = pomodoros.unwrap_or_default();
+                total_pomodoros += pomodoros;
+                if task.estimate > 0 {
+                    ui.label(format!("{}/{} pomodoros", pomodoros, task.estimate));
+                } else {
+                    ui.label(format!("{} pomodoros", pomodoros));
+                }
+                ui.end_row();
+            }
+        });
+        ui.separator();
+        ui.label(format!(
+            "{} tasks, {} pomodoros",
+            self.done_tasks.len(),
+            total_pomodoros
+        ));
+        if let Some(id) = reopened {
+            self.errors.report(
+                "Could not update task",
+                self.store.set_task_status(false, id),
+            );
+            self.reload();
+            update_ui = true;
+        }
+        if update_ui {
+            self.reload_done();
+        }
+    }
+
+    fn show_timer_bar(&mut self, ui: &mut egui::Ui, timers: &[Timer]) {
+        ui.horizontal(|ui| {
+            ui.scope(|ui| {
+                ui.style_mut().visuals.widgets.hovered.weak_bg_fill =
+                    Color32::from_hex("#A80000").unwrap();
+                let focus_button = if !timers.is_empty() && timers[0].is_pomodoro() {
+                    Button::fill(
+                        Button::new(
+                            RichText::new(format!("Focus x{}", self.pomodoros_estimate))
+                                .color(Color32::from_hex("#FFF9F0").unwrap()),
+                        ),
+                        Color32::from_hex("#A80000").unwrap(),
+                    )
+                } else {
+                    Button::new(format!("Focus x{}", self.pomodoros_estimate))
+                };
+                if ui
+                    .add(focus_button)
+                    .on_hover_cursor(egui::CursorIcon::PointingHand)
+                    .clicked()
+                {
+                    self.queue_timer(TimerKind::Focus);
+                }
+            });
+            ui.scope(|ui| {
+                ui.style_mut().visuals.widgets.hovered.weak_bg_fill =
+                    Color32::from_hex("#005C00").unwrap();
+                let short_break_button = if !timers.is_empty()
+                    && !timers[0].is_pomodoro()
+                    && timers[0].duration == self.configuration.short_break_duration
+                {
+                    Button::fill(
+                        Button::new(
+                            RichText::new("Short Break")
+                                .color(Color32::from_hex("#FFF9F0").unwrap()),
+                        ),
+                        Color32::from_hex("#005C00").unwrap(),
+                    )
+                } else {
+                    Button::new("Short Break")
+                };
+                if ui
+                    .add(short_break_button)
+                    .on_hover_cursor(egui::CursorIcon::PointingHand)
+                    .clicked()
+                {
+                    self.queue_timer(TimerKind::ShortBreak);
+                }
+            });
+            ui.scope(|ui| {
+                ui.style_mut().visuals.widgets.hovered.weak_bg_fill =
+                    Color32::from_hex("#1F1FFF").unwrap();
+                let long_break_button = if !timers.is_empty()
+                    && !timers[0].is_pomodoro()
+                    && timers[0].duration == self.configuration.long_break_duration
+                {
+                    Button::fill(
+                        Button::new(
+                            RichText::new("Long Break")
+                                .color(Color32::from_hex("#FFF9F0").unwrap()),
+                        ),
+                        Color32::from_hex("#1F1FFF").unwrap(),
+                    )
+                } else {
+                    Button::new("Long Break")
+                };
+                if ui
+                    .add(long_break_button)
+                    .on_hover_cursor(egui::CursorIcon::PointingHand)
+                    .clicked()
+                {
+                    self.queue_timer(TimerKind::LongBreak);
+                }
+            });
+            if !timers.is_empty() {
+                let timer = &timers[0];
+                let now = now();
+                if (now - self.last_checked_time).whole_milliseconds() >= 300 {
+                    let difference = timer.remaining(now);
+                    let seconds = difference.whole_seconds() % 60;
+                    let minutes = (difference.whole_seconds() / 60) % 60;
+                    if difference.whole_seconds() <= 0 {
+                        self.finish_timer(timer);
+                        if self.notified_timer != Some(timer.id) {
+                            self.errors
+                                .report("Could not play notification", play_notificaiton());
+                            self.notified_timer = Some(timer.id);
+                            if self.configuration.cycle_mode {
+                                self.schedule_next(timer.kind);
+                            }
+                            ui.ctx().send_viewport_cmd(egui::ViewportCommand::Title(
                                 DEFAULT_WINDOW_TITLE.to_string(),
                             ));
                         }
+                    } else {
+                        self.timer_value = format!("{:0>2}:{:0>2}", minutes, seconds);
+                        if timer.is_paused() {
+                            ui.ctx()
+                                .send_viewport_cmd(egui::ViewportCommand::Title(format!(
+                                    "{:0>2}:{:0>2} Paused",
+                                    minutes, seconds
+                                )));
+                        } else if timer.is_pomodoro() {
+                            ui.ctx()
+                                .send_viewport_cmd(egui::ViewportCommand::Title(format!(
+                                    "{:0>2}:{:0>2} Focus",
+                                    minutes, seconds
+                                )));
+                        } else {
+                            ui.ctx()
+                                .send_viewport_cmd(egui::ViewportCommand::Title(format!(
+                                    "{:0>2}:{:0>2}",
+                                    minutes, seconds
+                                )));
+                        }
                     }
-                    for timer in self.timers_queue.iter() {
-                        ui.label(timer.duration.to_string());
+                    self.last_checked_time = now;
+                }
+                ui.label(self.timer_value.clone());
+                ui.ctx()
+                    .request_repaint_after(std::time::Duration::from_millis(300));
+                if timer.remaining(now).whole_seconds() > 0 {
+                    let label = if timer.is_paused() { "Resume" } else { "Pause" };
+                    if ui
+                        .add(egui::Button::frame(egui::Button::new(label), false))
+                        .on_hover_cursor(egui::CursorIcon::PointingHand)
+                        .clicked()
+                    {
+                        let result = if timer.is_paused() {
+                            self.store.resume_timer(timer.id, now)
+                        } else {
+                            self.store.pause_timer(timer.id, now)
+                        };
+                        self.errors.report("Could not pause timer", result);
+                        // Redraw the countdown right away instead of after 300ms.
+                        self.last_checked_time = now - Duration::seconds(1);
+                    }
+                    if timer.is_pomodoro() {
+                        self.show_interruptions(ui, timer, now);
                     }
+                }
+                if timer.is_pomodoro() {
+                    self.show_stop_menu(ui, timer, now);
+                } else if ui
+                    .add(egui::Button::frame(egui::Button::new("x"), false))
+                    .on_hover_cursor(egui::CursorIcon::PointingHand)
+                    .clicked()
+                {
+                    self.errors.report(
+                        "Could not stop timer",
+                        self.store.set_timer_status(
+                            timer.id,
+                            timer.stopped_status(now),
+                            String::new(),
+                        ),
+                    );
+                    ui.ctx().send_viewport_cmd(egui::ViewportCommand::Title(
+                        DEFAULT_WINDOW_TITLE.to_string(),
+                    ));
+                }
+            }
+            if self.configuration.cycle_mode {
+                if let Some(next) = self.next_timer {
+                    if timers.is_empty()
+                        && ui
+                            .button(format!("Start {}", next.label()))
+                            .on_hover_cursor(egui::CursorIcon::PointingHand)
+                            .clicked()
+                    {
+                        self.queue_timer(next);
+                    }
+                }
+                ui.label(format!(
+                    "{}/{}",
+                    self.cycle.completed, self.configuration.long_break_interval
+                ))
+                .on_hover_text("Focus sessions since the last long break");
+            }
+        });
+    }
+}
+
+impl MyApp {
+    /// Ctrl+Z and Ctrl+Shift+Z, unless a text field has the keyboard and
+    /// wants them for its own text.
+    fn handle_undo_keys(&mut self, ctx: &egui::Context) {
+        if ctx.memory(|memory| memory.focused().is_some()) {
+            return;
+        }
+        // Ctrl+Z also matches with Shift held, so redo is checked first.
+        let (redo, undo) = ctx.input_mut(|i| {
+            (
+                i.consume_key(
+                    egui::Modifiers::COMMAND | egui::Modifiers::SHIFT,
+                    egui::Key::Z,
+                ),
+                i.consume_key(egui::Modifiers::COMMAND, egui::Key::Z),
+            )
+        });
+        if redo {
+            self.redo();
+        } else if undo {
+            self.undo();
+        }
+    }
+
+    /// Logs interruptions of the running focus timer and shows their marks.
+    fn show_interruptions(&mut self, ui: &mut egui::Ui, timer: &Timer, now: OffsetDateTime) {
+        ui.menu_button("Interrupted", |ui| {
+            ui.add(
+                egui::TextEdit::singleline(&mut self.interruption_note)
+                    .hint_text("What was it? (optional)"),
+            );
+            for (kind, hint) in [
+                (InterruptionKind::Internal, "An urge or idea of your own"),
+                (InterruptionKind::External, "Someone or something else"),
+            ] {
+                if ui
+                    .button(format!("{} {}", kind.mark(), kind.label()))
+                    .on_hover_text(hint)
+                    .clicked()
+                {
+                    let note = std::mem::take(&mut self.interruption_note);
+                    self.errors.report(
+                        "Could not log interruption",
+                        self.store.create_interruption(Interruption::new(
+                            timer.id,
+                            kind,
+                            now,
+                            note.trim().to_string(),
+                        )),
+                    );
+                    let result = self.refresh_totals();
+                    self.errors.report("Could not load totals", result);
+                    ui.close_menu();
+                }
+            }
+        });
+        let interruptions = self
+            .errors
+            .report(
+                "Could not load interruptions",
+                self.store.get_timer_interruptions(timer.id),
+            )
+            .unwrap_or_default();
+        if !interruptions.is_empty() {
+            ui.label(RichText::new(interruption_marks(&interruptions)).strong())
+                .on_hover_text(interruption_list(&interruptions));
+        }
+    }
+
+    /// Settles a timer that is over: a focus timer is credited to its task,
+    /// or waits for one, and a break ends.
+    fn finish_timer(&mut self, timer: &Timer) {
+        if let Some(task) = timer.task.filter(|_| timer.is_pomodoro()) {
+            self.errors.report(
+                "Could not add pomodoro to task",
+                self.store.update_timer_task(timer.id, task),
+            );
+            let result = self.refresh_totals();
+            self.errors.report("Could not load totals", result);
+        } else if timer.is_pomodoro() {
+            self.timer_value = "Done! Add point to task.".to_string();
+        } else {
+            self.errors.report(
+                "Could not end break",
+                self.store
+                    .set_timer_status(timer.id, TimerStatus::Completed, String::new()),
+            );
+        }
+    }
+
+    /// Stops the running focus timer: voided while it counts down, once over
+    /// credited to its task or abandoned without one. It stays on record.
+    fn show_stop_menu(&mut self, ui: &mut egui::Ui, timer: &Timer, now: OffsetDateTime) {
+        let status = timer.stopped_status(now);
+        let action = match status {
+            TimerStatus::Voided => "Void pomodoro",
+            TimerStatus::Completed => "Credit pomodoro",
+            _ => "Abandon pomodoro",
+        };
+        ui.menu_button("x", |ui| {
+            ui.add(egui::TextEdit::singleline(&mut self.stop_reason).hint_text("Why? (optional)"));
+            if ui.button(action).clicked() {
+                let reason = std::mem::take(&mut self.stop_reason);
+                self.errors.report(
+                    "Could not stop timer",
+                    self.store
+                        .set_timer_status(timer.id, status, reason.trim().to_string()),
+                );
+                ui.ctx().send_viewport_cmd(egui::ViewportCommand::Title(
+                    DEFAULT_WINDOW_TITLE.to_string(),
+                ));
+                ui.close_menu();
+            }
+        })
+        .response
+        .on_hover_text(action);
+    }
+
+    /// Names the latest edit for a few seconds, with a button to revert it.
+    fn show_undo_toast(&mut self, ctx: &egui::Context) {
+        if self.store.generation() != self.seen_generation {
+            self.seen_generation = self.store.generation();
+            self.undo_toast = self
+                .store
+                .undo_label()
+                .map(|label| (label.to_string(), now(), true));
+        }
+        let Some((label, shown_at, offer_undo)) = &self.undo_toast else {
+            return;
+        };
+        if now() - *shown_at > Duration::seconds(5) {
+            self.undo_toast = None;
+            return;
+        }
+        let mut undo = false;
+        egui::Area::new(egui::Id::new("undo_toast"))
+            .anchor(egui::Align2::RIGHT_BOTTOM, egui::vec2(-8.0, -48.0))
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label(label.as_str());
+                        if *offer_undo {
+                            undo = ui.button("Undo").clicked();
+                        }
+                    });
                 });
             });
+        if undo {
+            self.undo();
+        }
+        ctx.request_repaint_after(std::time::Duration::from_secs(1));
+    }
+}
 
-            if update_ui {
-                self.tasks = get_tasks(&self.conn);
-                self.pomodoros_estimate = get_pomodoros_median(&mut self.conn);
+impl eframe::App for MyApp {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        ctx.set_pixels_per_point(2.0);
+        let started = self.start_queued_timer(now());
+        if let Some(true) = self.errors.report("Could not start timer", started) {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Title(
+                DEFAULT_WINDOW_TITLE.to_string(),
+            ));
+        }
+        self.reload_if_stale(now());
+        self.handle_undo_keys(ctx);
+        ctx.request_repaint_after(std::time::Duration::from_secs(2));
+        let timers = self
+            .errors
+            .report("Could not load timers", self.store.get_running_timers())
+            .unwrap_or_default();
+        self.show_menu(ctx);
+        self.errors.show(ctx);
+        if self.view == View::Tasks {
+            self.show_projects(ctx);
+        }
+        self.show_queue(ctx);
+        self.show_import(ctx);
+        self.show_undo_toast(ctx);
+        egui::TopBottomPanel::bottom("timer_bar").show(ctx, |ui| {
+            self.show_timer_bar(ui, &timers);
+        });
+        egui::CentralPanel::default().show(ctx, |ui| match self.view {
+            View::Tasks | View::Today => self.show_tasks(ui, &timers),
+            View::Done => self.show_done(ui),
+            View::Statistics => {
+                stats_view::show(ui, &self.statistics, &self.estimates, now().date())
             }
         });
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_app() -> MyApp {
+        MyApp::new(
+            Box::new(InMemoryStore::new()),
+            Configuration::default(),
+            ErrorBanner::default(),
+        )
+    }
+
+    /// Adds a task through the new task input and names it. Returns its id.
+    fn add_named_task(app: &mut MyApp, name: &str) -> i32 {
+        app.new_task_name = name.to_string();
+        app.add_task().unwrap();
+        app.refresh().unwrap();
+        app.submit_task_name(0).unwrap();
+        app.tasks[0].id
+    }
+
+    fn focus_timer(duration: i32) -> Timer {
+        Timer::new(TimerKind::Focus, duration, OffsetDateTime::now_utc())
+    }
+
+    #[test]
+    fn queued_timer_starts_only_when_nothing_is_running() {
+        let mut app = test_app();
+        app.timers_queue.push(focus_timer(25));
+        app.timers_queue.push(focus_timer(25));
+
+        assert!(app.start_queued_timer(OffsetDateTime::now_utc()).unwrap());
+        assert!(!app.start_queued_timer(OffsetDateTime::now_utc()).unwrap());
+        assert_eq!(app.store.get_running_timers().unwrap().len(), 1);
+        assert_eq!(app.timers_queue.len(), 1);
+    }
+
+    #[test]
+    fn submitting_an_empty_name_deletes_the_task() {
+        let mut app = test_app();
+        app.add_task().unwrap();
+        app.refresh().unwrap();
+        assert_eq!(app.tasks.len(), 1);
+        assert!(app.show_new_task_input);
+
+        app.submit_task_name(0).unwrap();
+        app.refresh().unwrap();
+        assert!(app.tasks.is_empty());
+        assert!(!app.show_new_task_input);
+    }
+
+    #[test]
+    fn credited_timer_counts_towards_task() {
+        let mut app = test_app();
+        add_named_task(&mut app, "write report");
+        app.timers_queue.push(focus_timer(25));
+        app.start_queued_timer(OffsetDateTime::now_utc()).unwrap();
+
+        let timer = &app.store.get_running_timers().unwrap()[0];
+        let task_id = app.tasks[0].id;
+        app.store.update_timer_task(timer.id, task_id).unwrap();
+
+        assert_eq!(app.store.get_task_pomodoros(task_id).unwrap(), 1);
+        assert!(app.store.get_running_timers().unwrap().is_empty());
+    }
+
+    #[test]
+    fn focus_timer_is_started_for_the_current_task() {
+        let mut app = test_app();
+        let task_id = add_named_task(&mut app, "write report");
+        app.current_task = Some(task_id);
+        app.timers_queue.push(focus_timer(25));
+        app.start_queued_timer(OffsetDateTime::now_utc()).unwrap();
+
+        let timers = app.store.get_running_timers().unwrap();
+        assert_eq!(timers[0].task, Some(task_id));
+        assert_eq!(app.store.get_task_pomodoros(task_id).unwrap(), 0);
+    }
+
+    #[test]
+    fn undone_credit_is_not_credited_again() {
+        let mut app = test_app();
+        let task_id = add_named_task(&mut app, "write report");
+        app.current_task = Some(task_id);
+        app.timers_queue.push(focus_timer(25));
+        app.start_queued_timer(OffsetDateTime::now_utc() - Duration::minutes(30))
+            .unwrap();
+        let timer = app.store.get_running_timers().unwrap().remove(0);
+        app.finish_timer(&timer);
+        assert_eq!(app.store.get_task_pomodoros(task_id).unwrap(), 1);
+
+        app.undo();
+        // The next frame finishes whatever is still running and over.
+        for timer in app.store.get_running_timers().unwrap() {
+            app.finish_timer(&timer);
+        }
+        assert_eq!(app.store.get_task_pomodoros(task_id).unwrap(), 0);
+        assert_eq!(
+            app.store.redo().unwrap().as_deref(),
+            Some("Pomodoro credited")
+        );
+    }
+
+    #[test]
+    fn done_task_is_taken_off_the_running_timer() {
+        let mut app = test_app();
+        let task_id = add_named_task(&mut app, "write report");
+        app.current_task = Some(task_id);
+        app.timers_queue.push(focus_timer(25));
+        app.start_queued_timer(OffsetDateTime::now_utc()).unwrap();
+
+        app.store.set_task_status(true, task_id).unwrap();
+        assert_eq!(app.store.get_running_timers().unwrap()[0].task, None);
+        // Undoing the tick puts the task back on the timer.
+        app.store.undo().unwrap();
+        assert_eq!(
+            app.store.get_running_timers().unwrap()[0].task,
+            Some(task_id)
+        );
+    }
+
+    #[test]
+    fn deleted_current_task_is_taken_off_the_running_timer() {
+        let mut app = test_app();
+        app.current_task = Some(add_named_task(&mut app, "write report"));
+        app.timers_queue.push(focus_timer(25));
+        app.start_queued_timer(OffsetDateTime::now_utc()).unwrap();
+
+        app.refresh().unwrap();
+        app.tasks[0].name.clear();
+        app.submit_task_name(0).unwrap();
+
+        assert_eq!(app.current_task, None);
+        assert_eq!(app.store.get_running_timers().unwrap()[0].task, None);
+    }
+
+    #[test]
+    fn pauses_push_back_the_end_of_a_timer() {
+        let mut app = test_app();
+        let start = OffsetDateTime::now_utc();
+        app.timers_queue.push(focus_timer(25));
+        app.start_queued_timer(start).unwrap();
+        let id = app.store.get_running_timers().unwrap()[0].id;
+
+        app.store
+            .pause_timer(id, start + Duration::minutes(5))
+            .unwrap();
+        let timer = &app.store.get_running_timers().unwrap()[0];
+        assert!(timer.is_paused());
+        assert_eq!(
+            timer.remaining(start + Duration::minutes(50)),
+            Duration::minutes(20)
+        );
+
+        app.store
+            .resume_timer(id, start + Duration::minutes(15))
+            .unwrap();
+        let timer = &app.store.get_running_timers().unwrap()[0];
+        assert!(!timer.is_paused());
+        assert_eq!(timer.end(), start + Duration::minutes(35));
+    }
+
+    #[test]
+    fn finished_focus_schedules_the_next_break() {
+        let mut app = test_app();
+        app.configuration.long_break_interval = 2;
+        app.configuration.auto_start_breaks = true;
+
+        app.schedule_next(TimerKind::Focus);
+        assert_eq!(app.timers_queue.len(), 1);
+        assert!(!app.timers_queue[0].is_pomodoro());
+        assert_eq!(
+            app.timers_queue[0].duration,
+            app.configuration.short_break_duration
+        );
+        app.timers_queue.clear();
+
+        app.schedule_next(TimerKind::ShortBreak);
+        assert!(app.timers_queue.is_empty());
+        assert_eq!(app.next_timer, Some(TimerKind::Focus));
+
+        app.schedule_next(TimerKind::Focus);
+        assert_eq!(
+            app.timers_queue[0].duration,
+            app.configuration.long_break_duration
+        );
+    }
+
+    #[test]
+    fn queue_runs_first_in_first_out_and_persists() {
+        let mut app = test_app();
+        app.queue_timer(TimerKind::LongBreak);
+        app.queue_timer(TimerKind::Focus);
+        assert_eq!(app.store.get_queue().unwrap().len(), 2);
+
+        app.start_queued_timer(OffsetDateTime::now_utc()).unwrap();
+        let running = &app.store.get_running_timers().unwrap()[0];
+        assert_eq!(running.kind, TimerKind::LongBreak);
+        let queue = app.store.get_queue().unwrap();
+        assert_eq!(queue.len(), 1);
+        assert!(queue[0].is_pomodoro());
+    }
+
+    #[test]
+    fn clearing_a_name_can_be_undone() {
+        let mut app = test_app();
+        add_named_task(&mut app, "write report");
+        app.refresh().unwrap();
+
+        app.tasks[0].name.clear();
+        app.submit_task_name(0).unwrap();
+        app.refresh().unwrap();
+        assert!(app.tasks.is_empty());
+
+        app.undo();
+        assert_eq!(app.tasks[0].name, "write report");
+        app.redo();
+        assert!(app.tasks.is_empty());
+    }
+
+    #[test]
+    fn tasks_added_in_the_today_view_are_planned_for_today() {
+        let mut app = test_app();
+        app.view = View::Today;
+        app.new_task_name = "write report".to_string();
+        app.add_task().unwrap();
+        app.refresh().unwrap();
+        assert_eq!(app.tasks[0].planned, Some(now().date()));
+        assert!(app.tasks[0].is_for_today(now().date()));
+    }
+
+    #[test]
+    fn typed_tags_are_taken_out_of_the_name() {
+        let mut app = test_app();
+        app.add_task().unwrap();
+        app.refresh().unwrap();
+        app.tasks[0].name = "write #work report #deep".to_string();
+        app.submit_task_name(0).unwrap();
+        app.refresh().unwrap();
+        assert_eq!(app.tasks[0].name, "write report");
+        assert_eq!(app.tasks[0].tags, ["deep", "work"]);
+
+        app.undo();
+        assert!(app.tasks.is_empty());
+    }
+
+    #[test]
+    fn tasks_are_reordered_among_their_siblings() {
+        let mut app = test_app();
+        for name in ["a", "b", "c"] {
+            app.new_task_name = name.to_string();
+            app.add_task().unwrap();
+        }
+        app.refresh().unwrap();
+        let ids: Vec<i32> = app.tasks.iter().map(|task| task.id).collect();
+        let names = |app: &MyApp| -> Vec<String> {
+            app.tasks.iter().map(|task| task.name.clone()).collect()
+        };
+
+        app.move_task(ids[2], ids[0]).unwrap();
+        app.refresh().unwrap();
+        assert_eq!(names(&app), ["c", "a", "b"]);
+
+        app.selected_task = Some(ids[0]);
+        app.move_selected_task(false).unwrap();
+        app.refresh().unwrap();
+        assert_eq!(names(&app), ["c", "b", "a"]);
+
+        // Already at the bottom.
+        app.move_selected_task(false).unwrap();
+        app.refresh().unwrap();
+        assert_eq!(names(&app), ["c", "b", "a"]);
+    }
+
+    #[test]
+    fn day_typed_into_one_menu_is_not_carried_into_another() {
+        let mut input = (0, "", String::new());
+        day_input(&mut input, 1, "Due").push_str("2024-05");
+        assert_eq!(day_input(&mut input, 1, "Due"), "2024-05");
+        assert_eq!(day_input(&mut input, 1, "Plan for"), "");
+        day_input(&mut input, 1, "Plan for").push_str("2024-06");
+        assert_eq!(day_input(&mut input, 2, "Plan for"), "");
+    }
+
+    #[test]
+    fn selected_task_moves_past_filtered_out_siblings() {
+        let mut app = test_app();
+        for (name, tags) in [("a", vec!["work"]), ("b", vec![]), ("c", vec!["work"])] {
+            let tags = tags.into_iter().map(String::from).collect();
+            app.store
+                .create_task(Task {
+                    tags,
+                    ..Task::new(name)
+                })
+                .unwrap();
+        }
+        app.refresh().unwrap();
+        app.tag_filter.insert("work".to_string());
+
+        app.selected_task = Some(app.tasks[0].id);
+        app.move_selected_task(false).unwrap();
+        app.refresh().unwrap();
+        let names: Vec<&str> = app.tasks.iter().map(|task| task.name.as_str()).collect();
+        assert_eq!(names, ["b", "c", "a"]);
+    }
+}
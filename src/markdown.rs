@@ -0,0 +1,235 @@
+//! Just enough Markdown for task notes: `#` headings, `-`/`*`/`+` and
+//! numbered list items, `[ ]`/`[x]` checkboxes, fenced code blocks, and
+//! within a line `code`, **bold**, `[text](url)` and bare http(s) links.
+//! Every line break is kept, notes are read as written.
+use eframe::egui::{self, RichText};
+
+#[derive(Debug, PartialEq, Eq)]
+enum Block<'a> {
+    /// Level 1 to 6 and the text.
+    Heading(usize, &'a str),
+    /// A list item nested `depth` levels deep, with the marker shown before
+    /// it: a bullet, its number or a checkbox.
+    Item {
+        depth: usize,
+        marker: &'a str,
+        text: &'a str,
+    },
+    Code(Vec<&'a str>),
+    Line(&'a str),
+    Blank,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Span<'a> {
+    Text(&'a str),
+    Strong(&'a str),
+    Code(&'a str),
+    Link { text: &'a str, url: &'a str },
+}
+
+fn heading(line: &str) -> Option<(usize, &str)> {
+    let level = line.chars().take_while(|c| *c == '#').count();
+    let text = line[level..].strip_prefix(' ')?;
+    (1..=6).contains(&level).then_some((level, text.trim()))
+}
+
+fn item(line: &str) -> Option<(&str, &str)> {
+    let (marker, text) = if let Some(text) = ["- ", "* ", "+ "]
+        .iter()
+        .find_map(|bullet| line.strip_prefix(bullet))
+    {
+        ("•", text)
+    } else {
+        let digits = line.chars().take_while(char::is_ascii_digit).count();
+        let text = line[digits..].strip_prefix(". ").filter(|_| digits > 0)?;
+        (&line[..digits + 1], text)
+    };
+    Some(if let Some(text) = text.strip_prefix("[ ] ") {
+        ("☐", text)
+    } else if let Some(text) = ["[x] ", "[X] "]
+        .iter()
+        .find_map(|check| text.strip_prefix(check))
+    {
+        ("☑", text)
+    } else {
+        (marker, text)
+    })
+}
+
+fn blocks(text: &str) -> Vec<Block<'_>> {
+    let mut blocks = vec![];
+    let mut lines = text.lines();
+    while let Some(line) = lines.next() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") {
+            let code = lines
+                .by_ref()
+                .take_while(|line| !line.trim_start().starts_with("```"))
+                .collect();
+            blocks.push(Block::Code(code));
+        } else if let Some((level, text)) = heading(trimmed) {
+            blocks.push(Block::Heading(level, text));
+        } else if let Some((marker, text)) = item(trimmed) {
+            let depth = (line.len() - trimmed.len()) / 2;
+            blocks.push(Block::Item {
+                depth,
+                marker,
+                text,
+            });
+        } else if trimmed.is_empty() {
+            blocks.push(Block::Blank);
+        } else {
+            blocks.push(Block::Line(trimmed));
+        }
+    }
+    blocks
+}
+
+/// The span `rest` starts with and its length in bytes, if it is one.
+fn span_at(rest: &str) -> Option<(Span<'_>, usize)> {
+    if let Some(code) = rest.strip_prefix('`') {
+        let end = code.find('`')?;
+        Some((Span::Code(&code[..end]), end + 2))
+    } else if let Some(strong) = rest.strip_prefix("**") {
+        let end = strong.find("**").filter(|end| *end > 0)?;
+        Some((Span::Strong(&strong[..end]), end + 4))
+    } else if let Some(link) = rest.strip_prefix('[') {
+        let (text, url) = link.split_once("](")?;
+        let end = url.find(')').filter(|_| !text.contains(']'))?;
+        let url = &url[..end];
+        Some((Span::Link { text, url }, text.len() + url.len() + 4))
+    } else if rest.starts_with("https://") || rest.starts_with("http://") {
+        let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        // Punctuation right after a link usually ends the sentence.
+        let url = rest[..end].trim_end_matches(['.', ',', ';', ':', ')']);
+        Some((Span::Link { text: url, url }, url.len()))
+    } else {
+        None
+    }
+}
+
+fn spans(line: &str) -> Vec<Span<'_>> {
+    let mut spans = vec![];
+    let (mut text_start, mut index) = (0, 0);
+    while let Some(c) = line[index..].chars().next() {
+        if let Some((span, len)) = span_at(&line[index..]) {
+            if text_start < index {
+                spans.push(Span::Text(&line[text_start..index]));
+            }
+            spans.push(span);
+            index += len;
+            text_start = index;
+        } else {
+            index += c.len_utf8();
+        }
+    }
+    if text_start < line.len() {
+        spans.push(Span::Text(&line[text_start..]));
+    }
+    spans
+}
+
+fn show_line(ui: &mut egui::Ui, prefix: Option<(usize, &str)>, line: &str, size: Option<f32>) {
+    ui.horizontal_wrapped(|ui| {
+        if let Some((depth, marker)) = prefix {
+            ui.add_space(depth as f32 * 16.0);
+            ui.label(marker);
+        }
+        ui.spacing_mut().item_spacing.x = 0.0;
+        let style = |text: RichText| match size {
+            Some(size) => text.strong().size(size),
+            None => text,
+        };
+        for span in spans(line) {
+            match span {
+                Span::Text(text) => ui.label(style(RichText::new(text))),
+                Span::Strong(text) => ui.label(style(RichText::new(text).strong())),
+                Span::Code(text) => ui.label(style(RichText::new(text).code())),
+                Span::Link { text, url } => ui.hyperlink_to(style(RichText::new(text)), url),
+            };
+        }
+    });
+}
+
+/// Renders `text` as Markdown, one block under the other.
+pub fn show(ui: &mut egui::Ui, text: &str) {
+    for block in blocks(text) {
+        match block {
+            Block::Heading(level, text) => {
+                let size = [20.0, 17.0, 15.0][(level - 1).min(2)];
+                show_line(ui, None, text, Some(size));
+            }
+            Block::Item {
+                depth,
+                marker,
+                text,
+            } => show_line(ui, Some((depth, marker)), text, None),
+            Block::Code(lines) => {
+                egui::Frame::none()
+                    .fill(ui.visuals().code_bg_color)
+                    .inner_margin(4.0)
+                    .show(ui, |ui| {
+                        ui.label(RichText::new(lines.join("\n")).code());
+                    });
+            }
+            Block::Line(text) => show_line(ui, None, text, None),
+            Block::Blank => ui.add_space(ui.spacing().item_spacing.y),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blocks_are_told_apart() {
+        assert_eq!(
+            blocks(
+                "# Plan\n- [x] outline\n  2. draft\n\n```\nlet x = 1;\n```\n#tag, not a heading"
+            ),
+            [
+                Block::Heading(1, "Plan"),
+                Block::Item {
+                    depth: 0,
+                    marker: "☑",
+                    text: "outline"
+                },
+                Block::Item {
+                    depth: 1,
+                    marker: "2.",
+                    text: "draft"
+                },
+                Block::Blank,
+                Block::Code(vec!["let x = 1;"]),
+                Block::Line("#tag, not a heading"),
+            ]
+        );
+    }
+
+    #[test]
+    fn spans_pick_out_code_bold_and_links() {
+        assert_eq!(
+            spans("Run `cargo test`, **then** see [the docs](https://docs.rs) or https://example.com."),
+            [
+                Span::Text("Run "),
+                Span::Code("cargo test"),
+                Span::Text(", "),
+                Span::Strong("then"),
+                Span::Text(" see "),
+                Span::Link {
+                    text: "the docs",
+                    url: "https://docs.rs"
+                },
+                Span::Text(" or "),
+                Span::Link {
+                    text: "https://example.com",
+                    url: "https://example.com"
+                },
+                Span::Text("."),
+            ]
+        );
+        assert_eq!(spans("[ ] and `open"), [Span::Text("[ ] and `open")]);
+    }
+}
@@ -0,0 +1,196 @@
+use crate::error::{Error, Result};
+use rusqlite::Connection;
+
+// Each entry upgrades the schema by one version; entry `n` takes the database
+// from `user_version = n` to `user_version = n + 1`. Never edit a step that
+// has shipped, append a new one instead.
+const MIGRATIONS: &[&str] = &[
+    // v1: initial schema. Databases created before migrations existed already
+    // have these tables at user_version 0, hence IF NOT EXISTS.
+    "CREATE TABLE IF NOT EXISTS tasks (
+        id    INTEGER PRIMARY KEY,
+        name  TEXT NOT NULL,
+        done INTEGER,
+        estimate INTEGER,
+        locked INTEGER,
+        just_created INTEGER
+    );
+    CREATE TABLE IF NOT EXISTS timers (
+        id    INTEGER PRIMARY KEY,
+        is_pomodoro  INTEGER,
+        start INTEGER,
+        duration INTEGER,
+        task INTEGER,
+        FOREIGN KEY(task) REFERENCES tasks(id)
+    );",
+    // v2: pause intervals, `end` is NULL while the pause is in progress.
+    "CREATE TABLE pauses (
+        id    INTEGER PRIMARY KEY,
+        timer INTEGER NOT NULL,
+        start INTEGER NOT NULL,
+        end INTEGER,
+        FOREIGN KEY(timer) REFERENCES timers(id)
+    );",
+    // v3: timers waiting to run, in order.
+    "CREATE TABLE queue (
+        position INTEGER PRIMARY KEY,
+        is_pomodoro INTEGER NOT NULL,
+        duration INTEGER NOT NULL
+    );",
+    // v4: projects, tasks without one are in the inbox.
+    "CREATE TABLE projects (
+        id    INTEGER PRIMARY KEY,
+        name  TEXT NOT NULL
+    );
+    ALTER TABLE tasks ADD COLUMN project INTEGER REFERENCES projects(id);",
+    // v5: subtasks, top-level tasks have no parent.
+    "ALTER TABLE tasks ADD COLUMN parent INTEGER REFERENCES tasks(id);",
+    // v6: manual order of tasks, existing ones keep insertion order.
+    "ALTER TABLE tasks ADD COLUMN position INTEGER NOT NULL DEFAULT 0;
+    UPDATE tasks SET position = id;",
+    // v7: completion time of done tasks. Tasks done before it was recorded
+    // get the start of their last timer, if they have one.
+    "ALTER TABLE tasks ADD COLUMN completed_at TEXT;
+    UPDATE tasks SET completed_at = (SELECT max(start) FROM timers where timers.task = tasks.id)
+        where done = 1;",
+    // v8: due and planned days of tasks, as YYYY-MM-DD.
+    "ALTER TABLE tasks ADD COLUMN due TEXT;
+    ALTER TABLE tasks ADD COLUMN planned TEXT;",
+    // v9: recurrence rule of repeating tasks, see `Recurrence`.
+    "ALTER TABLE tasks ADD COLUMN recurrence TEXT;",
+    // v10: tags of tasks, without the leading '#'.
+    "CREATE TABLE task_tags (
+        task INTEGER NOT NULL REFERENCES tasks(id),
+        tag  TEXT NOT NULL,
+        PRIMARY KEY (task, tag)
+    );",
+    // v11: priority of tasks, 1 (most urgent) to 4.
+    "ALTER TABLE tasks ADD COLUMN priority INTEGER NOT NULL DEFAULT 4;",
+    // v12: notes of tasks, in Markdown.
+    "ALTER TABLE tasks ADD COLUMN notes TEXT NOT NULL DEFAULT '';",
+    // v13: interruptions logged during focus timers.
+    "CREATE TABLE interruptions (
+        id    INTEGER PRIMARY KEY,
+        timer INTEGER NOT NULL,
+        is_internal INTEGER NOT NULL,
+        at INTEGER NOT NULL,
+        note TEXT NOT NULL DEFAULT '',
+        FOREIGN KEY(timer) REFERENCES timers(id)
+    );",
+    // v14: how timers ended, instead of deleting the ones never credited.
+    "ALTER TABLE timers ADD COLUMN status TEXT NOT NULL DEFAULT 'running';
+    ALTER TABLE timers ADD COLUMN reason TEXT NOT NULL DEFAULT '';
+    UPDATE timers SET status = 'completed' where task is not NULL;",
+    // v15: kind of timers and queued timers, see `TimerKind`. Breaks from
+    // before it was recorded are taken for short ones.
+    "ALTER TABLE timers ADD COLUMN kind TEXT NOT NULL DEFAULT 'short_break';
+    UPDATE timers SET kind = 'focus' where is_pomodoro = 1;
+    ALTER TABLE queue ADD COLUMN kind TEXT NOT NULL DEFAULT 'short_break';
+    UPDATE queue SET kind = 'focus' where is_pomodoro = 1;",
+];
+
+pub const SCHEMA_VERSION: i32 = MIGRATIONS.len() as i32;
+
+pub fn schema_version(conn: &Connection) -> Result<i32> {
+    Ok(conn.pragma_query_value(None, "user_version", |row| row.get(0))?)
+}
+
+pub fn migrate(conn: &mut Connection) -> Result<()> {
+    let current = schema_version(conn)?;
+    if current > SCHEMA_VERSION {
+        return Err(Error::SchemaTooNew {
+            found: current,
+            supported: SCHEMA_VERSION,
+        });
+    }
+    for (version, step) in MIGRATIONS.iter().enumerate().skip(current as usize) {
+        let tx = conn.transaction()?;
+        tx.execute_batch(step)?;
+        tx.pragma_update(None, "user_version", version as i32 + 1)?;
+        tx.commit()?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Schema as written by setup_database before versioning was introduced.
+    const V0_FIXTURE: &str = "
+        CREATE TABLE tasks (
+            id    INTEGER PRIMARY KEY,
+            name  TEXT NOT NULL,
+            done INTEGER,
+            estimate INTEGER,
+            locked INTEGER,
+            just_created INTEGER
+        );
+        CREATE TABLE timers (
+            id    INTEGER PRIMARY KEY,
+            is_pomodoro  INTEGER,
+            start INTEGER,
+            duration INTEGER,
+            task INTEGER,
+            FOREIGN KEY(task) REFERENCES tasks(id)
+        );
+        INSERT INTO tasks (name, done, estimate, locked, just_created)
+            VALUES ('write report', 0, 3, 1, 0);
+        INSERT INTO timers (is_pomodoro, start, duration, task)
+            VALUES (1, '2024-05-01 09:00:00.0 +00:00:00', 25, 1);
+    ";
+
+    #[test]
+    fn fresh_database_migrates_to_head() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        migrate(&mut conn).unwrap();
+        assert_eq!(schema_version(&conn).unwrap(), SCHEMA_VERSION);
+    }
+
+    #[test]
+    fn v0_fixture_migrates_to_head_keeping_rows() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(V0_FIXTURE).unwrap();
+        assert_eq!(schema_version(&conn).unwrap(), 0);
+
+        migrate(&mut conn).unwrap();
+
+        assert_eq!(schema_version(&conn).unwrap(), SCHEMA_VERSION);
+        let name: String = conn
+            .query_row("SELECT name FROM tasks WHERE id = 1", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(name, "write report");
+        let timers: i32 = conn
+            .query_row("SELECT count(*) FROM timers WHERE task = 1", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(timers, 1);
+        let kind: String = conn
+            .query_row("SELECT kind FROM timers WHERE id = 1", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(kind, "focus");
+    }
+
+    #[test]
+    fn migrating_twice_is_a_no_op() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        migrate(&mut conn).unwrap();
+        migrate(&mut conn).unwrap();
+        assert_eq!(schema_version(&conn).unwrap(), SCHEMA_VERSION);
+    }
+
+    #[test]
+    fn refuses_newer_database() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.pragma_update(None, "user_version", SCHEMA_VERSION + 1)
+            .unwrap();
+        match migrate(&mut conn) {
+            Err(Error::SchemaTooNew { found, supported }) => {
+                assert_eq!(found, SCHEMA_VERSION + 1);
+                assert_eq!(supported, SCHEMA_VERSION);
+            }
+            other => panic!("expected SchemaTooNew, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,443 @@
+use crate::recurrence::Recurrence;
+use std::collections::HashSet;
+use time::macros::format_description;
+use time::{Date, Duration, OffsetDateTime};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Task {
+    pub id: i32,
+    pub name: String,
+    pub done: bool,
+    pub estimate: i32,
+    pub locked: bool,
+    pub just_created: bool,
+    /// `None` for tasks in the inbox.
+    pub project: Option<i32>,
+    /// `None` for top-level tasks.
+    pub parent: Option<i32>,
+    /// When the task was last ticked off, `None` while it is open.
+    pub completed_at: Option<OffsetDateTime>,
+    /// Place in the list among the other tasks, lowest first. Assigned by
+    /// the store when the task is created.
+    pub position: i32,
+    /// Day the task has to be done by.
+    pub due: Option<Date>,
+    /// Day the task is meant to be worked on.
+    pub planned: Option<Date>,
+    /// Set on tasks that come back once done. Only the latest occurrence
+    /// has it, earlier ones keep their pomodoros but don't repeat again.
+    pub recurrence: Option<Recurrence>,
+    /// Free-form labels, sorted and without the leading `#`.
+    pub tags: Vec<String>,
+    /// Urgency from 1 (P1, most urgent) to 4 (P4, the default). Open tasks
+    /// are listed by priority first, then by position.
+    pub priority: u8,
+    /// Longer context in Markdown, empty when there is none.
+    pub notes: String,
+}
+
+pub const DEFAULT_PRIORITY: u8 = 4;
+
+/// The priority after `priority` when cycling through them: one step more
+/// urgent each time, wrapping from P1 back to P4.
+pub fn cycle_priority(priority: u8) -> u8 {
+    if priority <= 1 {
+        DEFAULT_PRIORITY
+    } else {
+        priority - 1
+    }
+}
+
+impl Task {
+    /// An open task named `name` in the inbox, with the default priority and
+    /// nothing else set. Other fields are filled in with struct update syntax.
+    pub fn new(name: &str) -> Task {
+        Task {
+            id: 0,
+            name: name.to_string(),
+            done: false,
+            estimate: 0,
+            locked: true,
+            just_created: false,
+            project: None,
+            parent: None,
+            completed_at: None,
+            position: 0,
+            due: None,
+            planned: None,
+            recurrence: None,
+            tags: vec![],
+            priority: DEFAULT_PRIORITY,
+            notes: String::new(),
+        }
+    }
+
+    /// Open and due before `today`.
+    pub fn is_overdue(&self, today: Date) -> bool {
+        !self.done && self.due.is_some_and(|due| due < today)
+    }
+
+    /// Open and planned or due for `today` or earlier, so plans that slipped
+    /// stay on the list until they are done or moved.
+    pub fn is_for_today(&self, today: Date) -> bool {
+        !self.done
+            && (self.planned.is_some_and(|planned| planned <= today)
+                || self.due.is_some_and(|due| due <= today))
+    }
+
+    /// The task that follows this one when it is ticked off on `today`, if
+    /// it repeats. Its due and planned days move to the first day after
+    /// `today` the rule falls on, keeping the gap between them; a task
+    /// without either is planned for that day. Subtasks aren't repeated.
+    pub fn next_occurrence(&self, today: Date) -> Option<Task> {
+        let recurrence = self.recurrence.as_ref()?;
+        let anchor = self.due.or(self.planned).unwrap_or(today);
+        let mut next = recurrence.next_after(anchor);
+        while next <= today {
+            next = recurrence.next_after(next);
+        }
+        let shift = next - anchor;
+        Some(Task {
+            estimate: self.estimate,
+            project: self.project,
+            parent: self.parent,
+            due: self.due.map(|due| due + shift),
+            planned: match (self.due, self.planned) {
+                (None, None) => Some(next),
+                (_, planned) => planned.map(|planned| planned + shift),
+            },
+            recurrence: Some(recurrence.clone()),
+            tags: self.tags.clone(),
+            priority: self.priority,
+            notes: self.notes.clone(),
+            ..Task::new(&self.name)
+        })
+    }
+}
+
+#[derive(Clone)]
+pub struct Project {
+    pub id: i32,
+    pub name: String,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimerKind {
+    Focus,
+    ShortBreak,
+    LongBreak,
+}
+
+impl TimerKind {
+    pub const ALL: [TimerKind; 3] = [
+        TimerKind::Focus,
+        TimerKind::ShortBreak,
+        TimerKind::LongBreak,
+    ];
+
+    /// How the kind is stored.
+    pub fn name(&self) -> &'static str {
+        match self {
+            TimerKind::Focus => "focus",
+            TimerKind::ShortBreak => "short_break",
+            TimerKind::LongBreak => "long_break",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<TimerKind> {
+        TimerKind::ALL.into_iter().find(|kind| kind.name() == name)
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            TimerKind::Focus => "Focus",
+            TimerKind::ShortBreak => "Short Break",
+            TimerKind::LongBreak => "Long Break",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Timer {
+    pub id: i32,
+    pub kind: TimerKind,
+    pub start: OffsetDateTime,
+    pub duration: i32,
+    /// Task the timer is credited to, or while it runs the task being worked
+    /// on.
+    pub task: Option<i32>,
+    /// Total length of finished pauses.
+    pub paused: Duration,
+    /// Start of the pause in progress, if the timer is paused.
+    pub paused_at: Option<OffsetDateTime>,
+    pub status: TimerStatus,
+    /// Why a pomodoro was voided or abandoned, empty if no reason was given.
+    pub reason: String,
+}
+
+/// How a timer ended. Only completed pomodoros count towards tasks,
+/// statistics and the daily median, and only completed breaks as rest; the
+/// others are kept as a record.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimerStatus {
+    /// Counting down, or over and waiting to be credited.
+    Running,
+    Completed,
+    /// Stopped before the end, e.g. after an interruption.
+    Voided,
+    /// Over, but dismissed without crediting it to a task.
+    Abandoned,
+}
+
+impl TimerStatus {
+    pub const ALL: [TimerStatus; 4] = [
+        TimerStatus::Running,
+        TimerStatus::Completed,
+        TimerStatus::Voided,
+        TimerStatus::Abandoned,
+    ];
+
+    /// How the status is stored and exported.
+    pub fn name(&self) -> &'static str {
+        match self {
+            TimerStatus::Running => "running",
+            TimerStatus::Completed => "completed",
+            TimerStatus::Voided => "voided",
+            TimerStatus::Abandoned => "abandoned",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<TimerStatus> {
+        TimerStatus::ALL
+            .into_iter()
+            .find(|status| status.name() == name)
+    }
+}
+
+impl Timer {
+    /// A timer that isn't stored yet and isn't credited to a task.
+    pub fn new(kind: TimerKind, duration: i32, start: OffsetDateTime) -> Self {
+        Timer {
+            id: 0,
+            kind,
+            start,
+            duration,
+            task: None,
+            paused: Duration::ZERO,
+            paused_at: None,
+            status: TimerStatus::Running,
+            reason: String::new(),
+        }
+    }
+
+    /// Whether this is a focus timer rather than a break.
+    pub fn is_pomodoro(&self) -> bool {
+        self.kind == TimerKind::Focus
+    }
+
+    /// What stopping the timer at `now` makes of it: voided while it is
+    /// counting down. Once over, a break or a pomodoro with a task is
+    /// completed, and one without a task is abandoned.
+    pub fn stopped_status(&self, now: OffsetDateTime) -> TimerStatus {
+        if self.remaining(now) > Duration::ZERO {
+            TimerStatus::Voided
+        } else if self.is_pomodoro() && self.task.is_none() {
+            TimerStatus::Abandoned
+        } else {
+            TimerStatus::Completed
+        }
+    }
+
+    /// When the timer finishes, pushed back by every finished pause.
+    pub fn end(&self) -> OffsetDateTime {
+        self.start + Duration::minutes(self.duration.into()) + self.paused
+    }
+
+    /// Time left at `now`; frozen while the timer is paused.
+    pub fn remaining(&self, now: OffsetDateTime) -> Duration {
+        self.end() - self.paused_at.unwrap_or(now)
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused_at.is_some()
+    }
+
+    /// Folds a stored pause interval into `paused`/`paused_at`.
+    pub fn add_pause(&mut self, start: OffsetDateTime, end: Option<OffsetDateTime>) {
+        match end {
+            Some(end) => self.paused += end - start,
+            None => self.paused_at = Some(start),
+        }
+    }
+}
+
+/// Interruptions are marked the Pomodoro Technique way: `'` for internal
+/// ones, an urge or idea of your own, `-` for external ones.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InterruptionKind {
+    Internal,
+    External,
+}
+
+impl InterruptionKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            InterruptionKind::Internal => "Internal",
+            InterruptionKind::External => "External",
+        }
+    }
+
+    pub fn mark(&self) -> &'static str {
+        match self {
+            InterruptionKind::Internal => "'",
+            InterruptionKind::External => "-",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Interruption {
+    pub id: i32,
+    /// The focus timer that was interrupted.
+    pub timer: i32,
+    pub kind: InterruptionKind,
+    pub at: OffsetDateTime,
+    /// What it was about, empty when nothing was noted.
+    pub note: String,
+}
+
+impl Interruption {
+    /// An interruption of `timer` that isn't stored yet.
+    pub fn new(timer: i32, kind: InterruptionKind, at: OffsetDateTime, note: String) -> Self {
+        Interruption {
+            id: 0,
+            timer,
+            kind,
+            at,
+            note,
+        }
+    }
+}
+
+/// The marks of `interruptions` in a row, e.g. `''-`.
+pub fn interruption_marks(interruptions: &[Interruption]) -> String {
+    interruptions
+        .iter()
+        .map(|interruption| interruption.kind.mark())
+        .collect()
+}
+
+/// Indices into `tasks` in display order, each task followed by its subtasks,
+/// with their nesting depth. Subtasks of tasks in `collapsed` are left out,
+/// subtasks whose parent isn't in `tasks` (e.g. because it is done) are shown
+/// at the top level.
+pub fn task_tree(tasks: &[Task], collapsed: &HashSet<i32>) -> Vec<(usize, usize)> {
+    fn visit(
+        tasks: &[Task],
+        collapsed: &HashSet<i32>,
+        index: usize,
+        depth: usize,
+        tree: &mut Vec<(usize, usize)>,
+    ) {
+        tree.push((index, depth));
+        let id = tasks[index].id;
+        if collapsed.contains(&id) {
+            return;
+        }
+        for (child, task) in tasks.iter().enumerate() {
+            if task.parent == Some(id) {
+                visit(tasks, collapsed, child, depth + 1, tree);
+            }
+        }
+    }
+
+    let mut tree: Vec<(usize, usize)> = vec![];
+    for (index, task) in tasks.iter().enumerate() {
+        let is_root = task
+            .parent
+            .is_none_or(|parent| tasks.iter().all(|task| task.id != parent));
+        if is_root {
+            visit(tasks, collapsed, index, 0, &mut tree);
+        }
+    }
+    tree
+}
+
+/// Takes the `#tag` words out of a task name as typed, returning the rest
+/// of the name and the tags, sorted and without duplicates.
+pub fn split_tags(text: &str) -> (String, Vec<String>) {
+    let mut words: Vec<&str> = vec![];
+    let mut tags: Vec<String> = vec![];
+    for word in text.split_whitespace() {
+        match word.strip_prefix('#') {
+            Some(tag) if !tag.is_empty() && !tag.starts_with('#') => tags.push(tag.to_string()),
+            _ => words.push(word),
+        }
+    }
+    tags.sort();
+    tags.dedup();
+    (words.join(" "), tags)
+}
+
+/// Parses a `YYYY-MM-DD` date as typed by the user.
+pub fn parse_date(text: &str) -> Option<Date> {
+    Date::parse(text.trim(), format_description!("[year]-[month]-[day]")).ok()
+}
+
+/// Current local time, falling back to UTC when the local offset can't be
+/// determined (e.g. on Unix once other threads are running).
+pub fn now() -> OffsetDateTime {
+    OffsetDateTime::now_local().unwrap_or_else(|_| OffsetDateTime::now_utc())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task(id: i32, parent: Option<i32>) -> Task {
+        Task {
+            id,
+            parent,
+            ..Task::new(&id.to_string())
+        }
+    }
+
+    #[test]
+    fn subtasks_follow_their_parent() {
+        // 4's parent is done and not in the list.
+        let tasks = [
+            task(3, Some(1)),
+            task(1, None),
+            task(2, Some(3)),
+            task(4, Some(9)),
+        ];
+        assert_eq!(
+            task_tree(&tasks, &HashSet::new()),
+            [(1, 0), (0, 1), (2, 2), (3, 0)]
+        );
+        assert_eq!(
+            task_tree(&tasks, &HashSet::from([3])),
+            [(1, 0), (0, 1), (3, 0)]
+        );
+    }
+
+    #[test]
+    fn today_has_planned_due_and_overdue_tasks() {
+        let today = parse_date("2024-05-10").unwrap();
+        let mut planned = task(1, None);
+        planned.planned = parse_date("2024-05-09");
+        let mut due = task(2, None);
+        due.due = Some(today);
+        let mut later = task(3, None);
+        later.due = parse_date("2024-05-11");
+        later.planned = parse_date("2024-05-11");
+
+        assert!(planned.is_for_today(today) && !planned.is_overdue(today));
+        assert!(due.is_for_today(today) && !due.is_overdue(today));
+        assert!(!later.is_for_today(today));
+        assert!(due.is_overdue(today.next_day().unwrap()));
+
+        due.done = true;
+        assert!(!due.is_for_today(today) && !due.is_overdue(today.next_day().unwrap()));
+    }
+}
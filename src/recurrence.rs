@@ -0,0 +1,170 @@
+use std::fmt;
+use time::{Date, Duration, Weekday};
+
+const WEEKDAYS: [(Weekday, &str); 7] = [
+    (Weekday::Monday, "mon"),
+    (Weekday::Tuesday, "tue"),
+    (Weekday::Wednesday, "wed"),
+    (Weekday::Thursday, "thu"),
+    (Weekday::Friday, "fri"),
+    (Weekday::Saturday, "sat"),
+    (Weekday::Sunday, "sun"),
+];
+
+/// How often a task comes back once it is done.
+///
+/// Written as a rule: `daily`, `weekdays`, `weekly:mon,thu` or `every:3`
+/// for every 3 days. That is how it is stored, exported and typed in.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Recurrence {
+    Daily,
+    /// Monday to Friday.
+    Weekdays,
+    /// On the given days, Monday first and never empty.
+    Weekly(Vec<Weekday>),
+    /// Every `n` days, at least 1.
+    EveryDays(u32),
+}
+
+impl Recurrence {
+    /// Weekly on `days`, or `None` without any.
+    pub fn weekly(days: &[Weekday]) -> Option<Recurrence> {
+        let days: Vec<Weekday> = WEEKDAYS
+            .iter()
+            .map(|(day, _)| *day)
+            .filter(|day| days.contains(day))
+            .collect();
+        (!days.is_empty()).then_some(Recurrence::Weekly(days))
+    }
+
+    pub fn parse(rule: &str) -> Option<Recurrence> {
+        match rule.trim().to_lowercase().as_str() {
+            "daily" => Some(Recurrence::Daily),
+            "weekdays" => Some(Recurrence::Weekdays),
+            rule => {
+                if let Some(days) = rule.strip_prefix("weekly:") {
+                    let days = days
+                        .split(',')
+                        .map(|name| {
+                            WEEKDAYS
+                                .iter()
+                                .find(|(_, short)| *short == name.trim())
+                                .map(|(day, _)| *day)
+                        })
+                        .collect::<Option<Vec<Weekday>>>()?;
+                    Recurrence::weekly(&days)
+                } else {
+                    let days: u32 = rule.strip_prefix("every:")?.parse().ok()?;
+                    (days > 0).then_some(Recurrence::EveryDays(days))
+                }
+            }
+        }
+    }
+
+    /// For people, e.g. "weekly on Mon, Thu".
+    pub fn label(&self) -> String {
+        match self {
+            Recurrence::Daily => "daily".to_string(),
+            Recurrence::Weekdays => "on weekdays".to_string(),
+            Recurrence::Weekly(days) => {
+                let days: Vec<String> = days
+                    .iter()
+                    .map(|day| day.to_string()[..3].to_string())
+                    .collect();
+                format!("weekly on {}", days.join(", "))
+            }
+            Recurrence::EveryDays(1) => "daily".to_string(),
+            Recurrence::EveryDays(days) => format!("every {} days", days),
+        }
+    }
+
+    /// The first day after `day` the rule falls on.
+    pub fn next_after(&self, day: Date) -> Date {
+        if let Recurrence::EveryDays(days) = self {
+            return day + Duration::days((*days).max(1) as i64);
+        }
+        let matches = |candidate: Date| match self {
+            Recurrence::Weekdays => {
+                !matches!(candidate.weekday(), Weekday::Saturday | Weekday::Sunday)
+            }
+            Recurrence::Weekly(days) => days.contains(&candidate.weekday()),
+            Recurrence::Daily | Recurrence::EveryDays(_) => true,
+        };
+        let mut next = day + Duration::days(1);
+        // Any rule falls within a week; the bound guards an empty Weekly.
+        for _ in 0..7 {
+            if matches(next) {
+                break;
+            }
+            next += Duration::days(1);
+        }
+        next
+    }
+}
+
+impl fmt::Display for Recurrence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Recurrence::Daily => write!(f, "daily"),
+            Recurrence::Weekdays => write!(f, "weekdays"),
+            Recurrence::Weekly(days) => {
+                let days: Vec<&str> = days
+                    .iter()
+                    .filter_map(|day| {
+                        WEEKDAYS
+                            .iter()
+                            .find(|(weekday, _)| weekday == day)
+                            .map(|(_, short)| *short)
+                    })
+                    .collect();
+                write!(f, "weekly:{}", days.join(","))
+            }
+            Recurrence::EveryDays(days) => write!(f, "every:{}", days),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::macros::date;
+
+    #[test]
+    fn rules_round_trip() {
+        for rule in ["daily", "weekdays", "weekly:mon,thu", "every:3"] {
+            assert_eq!(Recurrence::parse(rule).unwrap().to_string(), rule);
+        }
+        assert_eq!(
+            Recurrence::parse("weekly:thu,mon"),
+            Recurrence::parse("weekly:mon,thu")
+        );
+        for rule in ["", "weekly:", "weekly:someday", "every:0", "hourly"] {
+            assert_eq!(Recurrence::parse(rule), None);
+        }
+    }
+
+    #[test]
+    fn next_day_follows_the_rule() {
+        // 2024-05-10 is a Friday.
+        let friday = date!(2024 - 05 - 10);
+        assert_eq!(Recurrence::Daily.next_after(friday), date!(2024 - 05 - 11));
+        assert_eq!(
+            Recurrence::Weekdays.next_after(friday),
+            date!(2024 - 05 - 13)
+        );
+        assert_eq!(
+            Recurrence::parse("weekly:mon,fri")
+                .unwrap()
+                .next_after(friday),
+            date!(2024 - 05 - 13)
+        );
+        assert_eq!(
+            Recurrence::parse("weekly:fri").unwrap().next_after(friday),
+            date!(2024 - 05 - 17)
+        );
+        assert_eq!(
+            Recurrence::EveryDays(3).next_after(friday),
+            date!(2024 - 05 - 13)
+        );
+    }
+}
@@ -0,0 +1,223 @@
+use crate::model::{Timer, TimerStatus};
+use std::collections::BTreeMap;
+
+/// Aggregates over completed timers since a given day, see `Store::get_statistics`.
+#[derive(Default)]
+pub struct Statistics {
+    /// Pomodoros per local day ("2024-05-01"), oldest first.
+    pub days: Vec<(String, i32)>,
+    /// Pomodoros per Monday-based week ("2024-W18"), oldest first.
+    pub weeks: Vec<(String, i32)>,
+    /// Pomodoros per month ("2024-05"), oldest first.
+    pub months: Vec<(String, i32)>,
+    pub focus_minutes: i64,
+    /// Minutes of completed breaks; breaks cut short don't count.
+    pub break_minutes: i64,
+    /// Focus and rest per Monday-based week, oldest first.
+    pub rest: Vec<RestTotal>,
+    /// Most worked-on tasks first.
+    pub tasks: Vec<TaskTotal>,
+    /// Totals per tag, named after the tag, most worked-on first. A pomodoro
+    /// counts towards every tag of its task.
+    pub tags: Vec<TaskTotal>,
+    /// Interruptions logged during the pomodoros counted above.
+    pub internal_interruptions: i32,
+    pub external_interruptions: i32,
+    /// Voided and abandoned pomodoros, most recent first. None of the
+    /// counts above include them.
+    pub stopped: Vec<Timer>,
+}
+
+pub struct TaskTotal {
+    pub name: String,
+    pub pomodoros: i32,
+    pub minutes: i64,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct RestTotal {
+    /// "2024-W18", as in `Statistics::weeks`.
+    pub week: String,
+    pub focus_minutes: i64,
+    pub break_minutes: i64,
+    /// Pomodoros followed by another one on the same day with no completed
+    /// break in between.
+    pub skipped_breaks: i32,
+}
+
+impl RestTotal {
+    pub fn ratio(&self) -> Option<f32> {
+        rest_ratio(self.focus_minutes, self.break_minutes)
+    }
+}
+
+/// Minutes of break per minute of focus, `None` without any focus.
+pub fn rest_ratio(focus_minutes: i64, break_minutes: i64) -> Option<f32> {
+    (focus_minutes > 0).then(|| break_minutes as f32 / focus_minutes as f32)
+}
+
+pub struct EstimateRecord {
+    pub name: String,
+    pub estimate: i32,
+    pub actual: i32,
+    /// Local day the task was completed, or of its last pomodoro for tasks
+    /// done before completion times were recorded.
+    pub finished: Option<String>,
+}
+
+fn mean(numbers: &[i32]) -> f32 {
+    let sum: i32 = numbers.iter().sum();
+
+    sum as f32 / numbers.len() as f32
+}
+
+pub fn median(numbers: &mut [i32]) -> i32 {
+    numbers.sort();
+
+    let mid = numbers.len() / 2;
+    if numbers.len().is_multiple_of(2) {
+        mean(&[numbers[mid - 1], numbers[mid]]) as i32
+    } else {
+        numbers[mid]
+    }
+}
+
+/// Actual pomodoros per estimated one; above 1 means tasks took longer than planned.
+pub fn estimate_ratio(records: &[EstimateRecord]) -> Option<f32> {
+    let estimated: i32 = records.iter().map(|record| record.estimate).sum();
+    let actual: i32 = records.iter().map(|record| record.actual).sum();
+    if estimated == 0 {
+        None
+    } else {
+        Some(actual as f32 / estimated as f32)
+    }
+}
+
+/// `estimate_ratio` per month ("2024-05") the tasks were finished in, oldest first.
+pub fn estimate_ratio_by_month(records: &[EstimateRecord]) -> Vec<(String, f32)> {
+    let mut months: BTreeMap<&str, (i32, i32)> = BTreeMap::new();
+    for record in records.iter() {
+        if let Some(finished) = &record.finished {
+            let (estimated, actual) = months.entry(&finished[..7]).or_default();
+            *estimated += record.estimate;
+            *actual += record.actual;
+        }
+    }
+    months
+        .into_iter()
+        .map(|(month, (estimated, actual))| (month.to_string(), actual as f32 / estimated as f32))
+        .collect()
+}
+
+/// `RestTotal`s of the completed timers among `timers`, in any order.
+pub fn rest_by_week(timers: &[Timer]) -> Vec<RestTotal> {
+    let mut timers: Vec<&Timer> = timers
+        .iter()
+        .filter(|timer| timer.status == TimerStatus::Completed)
+        .collect();
+    timers.sort_by_key(|timer| timer.start);
+    let mut weeks: BTreeMap<String, RestTotal> = BTreeMap::new();
+    // Day of the last pomodoro, until a break follows it.
+    let mut unrested = None;
+    for timer in timers {
+        let date = timer.start.date();
+        let week = format!("{}-W{:02}", date.year(), date.monday_based_week());
+        let total = weeks.entry(week.clone()).or_insert(RestTotal {
+            week,
+            focus_minutes: 0,
+            break_minutes: 0,
+            skipped_breaks: 0,
+        });
+        if timer.is_pomodoro() {
+            total.focus_minutes += i64::from(timer.duration);
+            if unrested == Some(date) {
+                total.skipped_breaks += 1;
+            }
+            unrested = Some(date);
+        } else {
+            total.break_minutes += i64::from(timer.duration);
+            unrested = None;
+        }
+    }
+    weeks.into_values().collect()
+}
+
+/// Estimate for a new task: the median of what recent tasks actually took.
+pub fn suggested_estimate(records: &[EstimateRecord]) -> Option<i32> {
+    let mut actuals: Vec<i32> = records
+        .iter()
+        .rev()
+        .take(20)
+        .map(|record| record.actual)
+        .filter(|actual| *actual > 0)
+        .collect();
+    if actuals.is_empty() {
+        None
+    } else {
+        Some(median(&mut actuals))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::TimerKind;
+    use time::macros::datetime;
+
+    fn record(estimate: i32, actual: i32, finished: &str) -> EstimateRecord {
+        EstimateRecord {
+            name: "task".to_string(),
+            estimate,
+            actual,
+            finished: Some(finished.to_string()),
+        }
+    }
+
+    #[test]
+    fn estimate_ratios_compare_actual_to_estimated() {
+        let records = vec![
+            record(2, 4, "2024-04-10"),
+            record(4, 4, "2024-05-02"),
+            record(4, 2, "2024-05-20"),
+        ];
+        assert_eq!(estimate_ratio(&records), Some(1.0));
+        assert_eq!(
+            estimate_ratio_by_month(&records),
+            vec![("2024-04".to_string(), 2.0), ("2024-05".to_string(), 0.75)]
+        );
+        assert_eq!(suggested_estimate(&records), Some(4));
+        assert_eq!(suggested_estimate(&[]), None);
+    }
+
+    #[test]
+    fn pomodoros_back_to_back_skip_a_break() {
+        let mut timers = vec![];
+        for (kind, start) in [
+            (TimerKind::Focus, datetime!(2024-05-01 09:00 +2)),
+            (TimerKind::ShortBreak, datetime!(2024-05-01 09:25 +2)),
+            (TimerKind::Focus, datetime!(2024-05-01 09:30 +2)),
+            (TimerKind::Focus, datetime!(2024-05-01 10:00 +2)),
+            // The next day starts afresh.
+            (TimerKind::Focus, datetime!(2024-05-02 09:00 +2)),
+        ] {
+            let mut timer = Timer::new(kind, if kind == TimerKind::Focus { 25 } else { 5 }, start);
+            timer.status = TimerStatus::Completed;
+            timers.push(timer);
+        }
+        let mut cut_short = Timer::new(TimerKind::ShortBreak, 5, datetime!(2024-05-01 09:55 +2));
+        cut_short.status = TimerStatus::Voided;
+        timers.push(cut_short);
+
+        let rest = rest_by_week(&timers);
+        assert_eq!(
+            rest,
+            [RestTotal {
+                week: "2024-W18".to_string(),
+                focus_minutes: 100,
+                break_minutes: 5,
+                skipped_breaks: 1,
+            }]
+        );
+        assert_eq!(rest[0].ratio(), Some(0.05));
+    }
+}
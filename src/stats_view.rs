@@ -0,0 +1,254 @@
+use eframe::egui::{self, Color32, Rect, RichText, Sense, Vec2};
+use pomodoro_todo_list::model::{Timer, TimerStatus};
+use pomodoro_todo_list::stats::{
+    estimate_ratio, estimate_ratio_by_month, rest_ratio, EstimateRecord, RestTotal, Statistics,
+};
+use time::{Date, Duration};
+
+const BAR_HEIGHT: f32 = 60.0;
+const CELL_SIZE: f32 = 10.0;
+
+fn focus_color(alpha: u8) -> Color32 {
+    Color32::from_rgba_unmultiplied(0xA8, 0x00, 0x00, alpha)
+}
+
+pub fn show(ui: &mut egui::Ui, statistics: &Statistics, estimates: &[EstimateRecord], today: Date) {
+    egui::ScrollArea::vertical().show(ui, |ui| {
+        ui.heading("Last 12 months");
+        ui.label(format!(
+            "Focus: {} min, breaks: {} min",
+            statistics.focus_minutes, statistics.break_minutes
+        ));
+        let skipped: i32 = statistics.rest.iter().map(|week| week.skipped_breaks).sum();
+        if let Some(ratio) = rest_ratio(statistics.focus_minutes, statistics.break_minutes) {
+            ui.label(format!(
+                "Rest: {:.2} min of break per min of focus, {} breaks skipped",
+                ratio, skipped
+            ));
+        }
+        let pomodoros: i32 = statistics.days.iter().map(|(_, count)| count).sum();
+        let interruptions = statistics.internal_interruptions + statistics.external_interruptions;
+        if interruptions > 0 {
+            ui.label(format!(
+                "Interruptions: {} internal ('), {} external (-), {:.1} per pomodoro",
+                statistics.internal_interruptions,
+                statistics.external_interruptions,
+                interruptions as f32 / pomodoros.max(1) as f32
+            ));
+        }
+        ui.separator();
+
+        ui.label("Pomodoros per day");
+        let days: Vec<(String, i32)> = (0..14)
+            .rev()
+            .map(|offset| {
+                let day = (today - Duration::days(offset)).to_string();
+                let count = count_for(&statistics.days, &day);
+                (day, count)
+            })
+            .collect();
+        bar_chart(ui, &days);
+        ui.label("Pomodoros per week");
+        bar_chart(ui, last(&statistics.weeks, 12));
+        ui.label("Pomodoros per month");
+        bar_chart(ui, last(&statistics.months, 12));
+        ui.separator();
+
+        ui.label("Year");
+        heatmap(ui, &statistics.days, today);
+        ui.separator();
+
+        ui.label("Per task");
+        egui::Grid::new("task_totals").striped(true).show(ui, |ui| {
+            for task in statistics.tasks.iter() {
+                ui.label(&task.name);
+                ui.label(format!("{} pomodoros", task.pomodoros));
+                ui.label(format!("{} min", task.minutes));
+                ui.end_row();
+            }
+        });
+        if !statistics.tags.is_empty() {
+            ui.label("Per tag");
+            egui::Grid::new("tag_totals").striped(true).show(ui, |ui| {
+                for tag in statistics.tags.iter() {
+                    ui.label(format!("#{}", tag.name));
+                    ui.label(format!("{} pomodoros", tag.pomodoros));
+                    ui.label(format!("{} min", tag.minutes));
+                    ui.end_row();
+                }
+            });
+        }
+        ui.separator();
+
+        show_rest(
+            ui,
+            &statistics.rest[statistics.rest.len().saturating_sub(8)..],
+        );
+        show_stopped(ui, &statistics.stopped);
+        show_estimates(ui, estimates);
+    });
+}
+
+/// Breaks taken against focus time, week by week.
+fn show_rest(ui: &mut egui::Ui, weeks: &[RestTotal]) {
+    if weeks.is_empty() {
+        return;
+    }
+    ui.label("Rest per week");
+    egui::Grid::new("rest_per_week")
+        .striped(true)
+        .show(ui, |ui| {
+            for header in ["Week", "Focus", "Breaks", "Ratio", "Skipped"] {
+                ui.label(RichText::new(header).strong());
+            }
+            ui.end_row();
+            for week in weeks {
+                ui.label(&week.week);
+                ui.label(format!("{} min", week.focus_minutes));
+                ui.label(format!("{} min", week.break_minutes));
+                ui.label(
+                    week.ratio()
+                        .map_or("-".to_string(), |ratio| format!("{:.2}", ratio)),
+                );
+                ui.label(week.skipped_breaks.to_string());
+                ui.end_row();
+            }
+        });
+    ui.separator();
+}
+
+/// Voided and abandoned pomodoros, which none of the totals include.
+fn show_stopped(ui: &mut egui::Ui, stopped: &[Timer]) {
+    if stopped.is_empty() {
+        return;
+    }
+    let voided = stopped
+        .iter()
+        .filter(|timer| timer.status == TimerStatus::Voided)
+        .count();
+    ui.label(format!(
+        "Pomodoros not completed: {} voided, {} abandoned",
+        voided,
+        stopped.len() - voided
+    ));
+    egui::Grid::new("stopped_pomodoros")
+        .striped(true)
+        .show(ui, |ui| {
+            for timer in stopped.iter().take(10) {
+                ui.label(timer.start.date().to_string());
+                ui.label(timer.status.name());
+                ui.label(RichText::new(&timer.reason).weak());
+                ui.end_row();
+            }
+        });
+    ui.separator();
+}
+
+fn describe_ratio(ratio: f32) -> String {
+    if ratio > 1.05 {
+        format!("{:.2}x, underestimating", ratio)
+    } else if ratio < 0.95 {
+        format!("{:.2}x, overestimating", ratio)
+    } else {
+        format!("{:.2}x, on target", ratio)
+    }
+}
+
+fn show_estimates(ui: &mut egui::Ui, estimates: &[EstimateRecord]) {
+    ui.label("Estimates of finished tasks");
+    let Some(ratio) = estimate_ratio(estimates) else {
+        ui.label(RichText::new("No finished tasks with an estimate yet.").weak());
+        return;
+    };
+    ui.label(format!("Actual / estimated: {}", describe_ratio(ratio)));
+    egui::Grid::new("estimate_ratio_by_month")
+        .striped(true)
+        .show(ui, |ui| {
+            for (month, ratio) in estimate_ratio_by_month(estimates) {
+                ui.label(month);
+                ui.label(describe_ratio(ratio));
+                ui.end_row();
+            }
+        });
+    ui.add_space(8.0);
+    egui::Grid::new("estimates").striped(true).show(ui, |ui| {
+        ui.label(RichText::new("Task").strong());
+        ui.label(RichText::new("Estimated").strong());
+        ui.label(RichText::new("Actual").strong());
+        ui.end_row();
+        for record in estimates.iter().rev() {
+            ui.label(&record.name);
+            ui.label(record.estimate.to_string());
+            ui.label(record.actual.to_string());
+            ui.end_row();
+        }
+    });
+}
+
+fn count_for(counts: &[(String, i32)], period: &str) -> i32 {
+    counts
+        .iter()
+        .find(|(key, _)| key == period)
+        .map_or(0, |(_, count)| *count)
+}
+
+fn last(counts: &[(String, i32)], n: usize) -> &[(String, i32)] {
+    &counts[counts.len().saturating_sub(n)..]
+}
+
+fn bar_chart(ui: &mut egui::Ui, counts: &[(String, i32)]) {
+    let max = counts
+        .iter()
+        .map(|(_, count)| *count)
+        .max()
+        .unwrap_or(0)
+        .max(1);
+    ui.horizontal(|ui| {
+        for (period, count) in counts {
+            let (rect, response) =
+                ui.allocate_exact_size(Vec2::new(CELL_SIZE * 1.5, BAR_HEIGHT), Sense::hover());
+            let height = BAR_HEIGHT * *count as f32 / max as f32;
+            let bar = Rect::from_min_max(egui::pos2(rect.left(), rect.bottom() - height), rect.max);
+            ui.painter().rect_filled(bar, 2.0, focus_color(255));
+            response.on_hover_text(format!("{}: {}", period, count));
+        }
+    });
+}
+
+/// GitHub-style grid of the last year: one column per week, Monday on top.
+fn heatmap(ui: &mut egui::Ui, days: &[(String, i32)], today: Date) {
+    let first = today - Duration::days(364);
+    let first = first - Duration::days(first.weekday().number_days_from_monday().into());
+    let weeks = (today - first).whole_weeks() + 1;
+    let max = days
+        .iter()
+        .map(|(_, count)| *count)
+        .max()
+        .unwrap_or(0)
+        .max(1);
+    let (rect, _) = ui.allocate_exact_size(
+        Vec2::new(weeks as f32 * (CELL_SIZE + 2.0), 7.0 * (CELL_SIZE + 2.0)),
+        Sense::hover(),
+    );
+    let empty = ui.visuals().faint_bg_color;
+    let mut day = first;
+    while day <= today {
+        let week = (day - first).whole_weeks() as f32;
+        let weekday = day.weekday().number_days_from_monday() as f32;
+        let cell = Rect::from_min_size(
+            rect.min + Vec2::new(week * (CELL_SIZE + 2.0), weekday * (CELL_SIZE + 2.0)),
+            Vec2::splat(CELL_SIZE),
+        );
+        let key = day.to_string();
+        let count = count_for(days, &key);
+        let color = if count == 0 {
+            empty
+        } else {
+            focus_color((64 + 191 * count / max) as u8)
+        };
+        ui.painter().rect_filled(cell, 2.0, color);
+        let response = ui.interact(cell, ui.id().with(("heatmap", &key)), Sense::hover());
+        response.on_hover_text(format!("{}: {}", key, count));
+        day += Duration::days(1);
+    }
+}
@@ -0,0 +1,618 @@
+use super::Store;
+use crate::error::Result;
+use crate::model::{now, Interruption, InterruptionKind, Project, Task, Timer, TimerStatus};
+use crate::recurrence::Recurrence;
+use crate::stats::{median, rest_by_week, EstimateRecord, Statistics, TaskTotal};
+use std::collections::BTreeMap;
+use time::{Date, Duration, OffsetDateTime, UtcOffset};
+
+struct Pause {
+    timer: i32,
+    start: OffsetDateTime,
+    end: Option<OffsetDateTime>,
+}
+
+#[derive(Default)]
+pub struct InMemoryStore {
+    tasks: Vec<Task>,
+    timers: Vec<Timer>,
+    pauses: Vec<Pause>,
+    interruptions: Vec<Interruption>,
+    queue: Vec<Timer>,
+    projects: Vec<Project>,
+    last_task_id: i32,
+    last_project_id: i32,
+    last_timer_id: i32,
+    last_interruption_id: i32,
+}
+
+impl InMemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn completed_pomodoros(&self, since: Date) -> impl Iterator<Item = &Timer> {
+        self.timers.iter().filter(move |timer| {
+            timer.is_pomodoro()
+                && timer.status == TimerStatus::Completed
+                && timer.start.date() >= since
+        })
+    }
+
+    /// A copy of `timer` with its stored pauses folded in.
+    fn with_pauses(&self, timer: &Timer) -> Timer {
+        let mut timer = timer.clone();
+        timer.paused = Duration::ZERO;
+        timer.paused_at = None;
+        let id = timer.id;
+        for pause in self.pauses.iter().filter(|pause| pause.timer == id) {
+            timer.add_pause(pause.start, pause.end);
+        }
+        timer
+    }
+
+    /// `id` followed by the ids of its subtasks at any depth.
+    fn subtree(&self, id: i32) -> Vec<i32> {
+        let mut ids = vec![id];
+        let mut index = 0;
+        while index < ids.len() {
+            for task in self.tasks.iter() {
+                if task.parent == Some(ids[index]) && !ids.contains(&task.id) {
+                    ids.push(task.id);
+                }
+            }
+            index += 1;
+        }
+        ids
+    }
+
+    fn task_mut(&mut self, id: i32) -> Option<&mut Task> {
+        self.tasks.iter_mut().find(|task| task.id == id)
+    }
+
+    /// Takes a task that is done or deleted off running timers.
+    fn release_task(&mut self, id: i32) {
+        for timer in self.timers.iter_mut() {
+            if timer.task == Some(id) && timer.status == TimerStatus::Running {
+                timer.task = None;
+            }
+        }
+    }
+}
+
+impl Store for InMemoryStore {
+    fn get_tasks(&self) -> Result<Vec<Task>> {
+        let mut tasks: Vec<Task> = self
+            .tasks
+            .iter()
+            .filter(|task| !task.done)
+            .cloned()
+            .collect();
+        tasks.sort_by_key(|task| (task.priority, task.position, task.id));
+        Ok(tasks)
+    }
+
+    fn get_done_tasks(&self, from: Option<Date>, to: Option<Date>) -> Result<Vec<Task>> {
+        let mut tasks: Vec<Task> = self
+            .tasks
+            .iter()
+            .filter(|task| task.done)
+            .filter(|task| match (task.completed_at, from) {
+                (_, None) => true,
+                (None, Some(_)) => false,
+                (Some(completed_at), Some(from)) => completed_at.date() >= from,
+            })
+            .filter(|task| match (task.completed_at, to) {
+                (Some(completed_at), Some(to)) => completed_at.date() <= to,
+                _ => true,
+            })
+            .cloned()
+            .collect();
+        tasks.sort_by(|a, b| match (a.completed_at, b.completed_at) {
+            (Some(a), Some(b)) => b.cmp(&a),
+            (a, b) => a.is_none().cmp(&b.is_none()),
+        });
+        Ok(tasks)
+    }
+
+    fn get_all_tasks(&self) -> Result<Vec<Task>> {
+        Ok(self.tasks.clone())
+    }
+
+    fn create_task(&mut self, mut task: Task) -> Result<i32> {
+        self.last_task_id += 1;
+        task.id = self.last_task_id;
+        task.position = self
+            .tasks
+            .iter()
+            .map(|task| task.position)
+            .max()
+            .unwrap_or(0)
+            + 1;
+        self.tasks.push(task);
+        Ok(self.last_task_id)
+    }
+
+    fn set_task_status(&mut self, done: bool, id: i32) -> Result<()> {
+        let mut next = None;
+        if let Some(task) = self.task_mut(id) {
+            if done && !task.done {
+                next = task.next_occurrence(now().date());
+                if next.is_some() {
+                    task.recurrence = None;
+                }
+            }
+            task.done = done;
+            task.completed_at = if done { Some(now()) } else { None };
+        }
+        if done {
+            self.release_task(id);
+        }
+        if let Some(next) = next {
+            self.create_task(next)?;
+        }
+        Ok(())
+    }
+
+    fn set_task_locked(&mut self, locked: bool, id: i32) -> Result<()> {
+        if let Some(task) = self.task_mut(id) {
+            task.locked = locked;
+        }
+        Ok(())
+    }
+
+    fn set_task_just_created(&mut self, just_created: bool, id: i32) -> Result<()> {
+        if let Some(task) = self.task_mut(id) {
+            task.just_created = just_created;
+        }
+        Ok(())
+    }
+
+    fn set_task_name(&mut self, name: String, id: i32) -> Result<()> {
+        if let Some(task) = self.task_mut(id) {
+            task.name = name;
+        }
+        Ok(())
+    }
+
+    fn set_task_estimate(&mut self, estimate: i32, id: i32) -> Result<()> {
+        if let Some(task) = self.task_mut(id) {
+            task.estimate = estimate;
+        }
+        Ok(())
+    }
+
+    fn set_task_due(&mut self, due: Option<Date>, id: i32) -> Result<()> {
+        if let Some(task) = self.task_mut(id) {
+            task.due = due;
+        }
+        Ok(())
+    }
+
+    fn set_task_planned(&mut self, planned: Option<Date>, id: i32) -> Result<()> {
+        if let Some(task) = self.task_mut(id) {
+            task.planned = planned;
+        }
+        Ok(())
+    }
+
+    fn set_task_recurrence(&mut self, recurrence: Option<Recurrence>, id: i32) -> Result<()> {
+        if let Some(task) = self.task_mut(id) {
+            task.recurrence = recurrence;
+        }
+        Ok(())
+    }
+
+    fn set_task_tags(&mut self, tags: Vec<String>, id: i32) -> Result<()> {
+        if let Some(task) = self.task_mut(id) {
+            task.tags = tags;
+        }
+        Ok(())
+    }
+
+    fn set_task_priority(&mut self, priority: u8, id: i32) -> Result<()> {
+        if let Some(task) = self.task_mut(id) {
+            task.priority = priority;
+        }
+        Ok(())
+    }
+
+    fn set_task_notes(&mut self, notes: String, id: i32) -> Result<()> {
+        if let Some(task) = self.task_mut(id) {
+            task.notes = notes;
+        }
+        Ok(())
+    }
+
+    fn delete_task(&mut self, id: i32) -> Result<()> {
+        self.tasks.retain(|task| task.id != id);
+        self.release_task(id);
+        Ok(())
+    }
+
+    fn put_task(&mut self, task: &Task) -> Result<()> {
+        match self.task_mut(task.id) {
+            Some(stored) => *stored = task.clone(),
+            None => {
+                self.last_task_id = self.last_task_id.max(task.id);
+                self.tasks.push(task.clone());
+                self.tasks.sort_by_key(|task| task.id);
+            }
+        }
+        Ok(())
+    }
+
+    fn set_task_order(&mut self, ids: &[i32]) -> Result<()> {
+        let mut positions: Vec<i32> = self
+            .tasks
+            .iter()
+            .filter(|task| ids.contains(&task.id))
+            .map(|task| task.position)
+            .collect();
+        positions.sort();
+        for (id, position) in ids.iter().zip(positions) {
+            if let Some(task) = self.task_mut(*id) {
+                task.position = position;
+            }
+        }
+        Ok(())
+    }
+
+    fn set_task_project(&mut self, project: Option<i32>, id: i32) -> Result<()> {
+        for id in self.subtree(id) {
+            if let Some(task) = self.task_mut(id) {
+                task.project = project;
+            }
+        }
+        Ok(())
+    }
+
+    fn get_projects(&self) -> Result<Vec<Project>> {
+        let mut projects = self.projects.clone();
+        projects.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(projects)
+    }
+
+    fn create_project(&mut self, name: String) -> Result<()> {
+        self.last_project_id += 1;
+        self.projects.push(Project {
+            id: self.last_project_id,
+            name,
+        });
+        Ok(())
+    }
+
+    fn get_project_pomodoros(&self, project: Option<i32>) -> Result<usize> {
+        Ok(self
+            .timers
+            .iter()
+            .filter(|timer| timer.is_pomodoro() && timer.status == TimerStatus::Completed)
+            .filter(|timer| {
+                self.tasks
+                    .iter()
+                    .any(|task| Some(task.id) == timer.task && task.project == project)
+            })
+            .count())
+    }
+
+    fn get_running_timers(&self) -> Result<Vec<Timer>> {
+        Ok(self
+            .timers
+            .iter()
+            .filter(|timer| timer.status == TimerStatus::Running)
+            .map(|timer| self.with_pauses(timer))
+            .collect())
+    }
+
+    fn get_all_timers(&self) -> Result<Vec<Timer>> {
+        Ok(self
+            .timers
+            .iter()
+            .map(|timer| self.with_pauses(timer))
+            .collect())
+    }
+
+    fn get_timer(&self, id: i32) -> Result<Option<Timer>> {
+        Ok(self
+            .timers
+            .iter()
+            .find(|timer| timer.id == id)
+            .map(|timer| self.with_pauses(timer)))
+    }
+
+    fn create_timer(&mut self, mut timer: Timer) -> Result<()> {
+        self.last_timer_id += 1;
+        timer.id = self.last_timer_id;
+        self.timers.push(timer);
+        Ok(())
+    }
+
+    fn put_timer(&mut self, timer: &Timer) -> Result<()> {
+        // Not `delete_timer`, the interruptions stay.
+        self.pauses.retain(|pause| pause.timer != timer.id);
+        self.timers.retain(|other| other.id != timer.id);
+        self.last_timer_id = self.last_timer_id.max(timer.id);
+        if timer.paused > Duration::ZERO {
+            self.pauses.push(Pause {
+                timer: timer.id,
+                start: timer.start,
+                end: Some(timer.start + timer.paused),
+            });
+        }
+        if let Some(paused_at) = timer.paused_at {
+            self.pauses.push(Pause {
+                timer: timer.id,
+                start: paused_at,
+                end: None,
+            });
+        }
+        let mut timer = timer.clone();
+        timer.paused = Duration::ZERO;
+        timer.paused_at = None;
+        self.timers.push(timer);
+        self.timers.sort_by_key(|timer| timer.id);
+        Ok(())
+    }
+
+    fn delete_timer(&mut self, id: i32) -> Result<()> {
+        self.pauses.retain(|pause| pause.timer != id);
+        self.interruptions
+            .retain(|interruption| interruption.timer != id);
+        self.timers.retain(|timer| timer.id != id);
+        Ok(())
+    }
+
+    fn pause_timer(&mut self, timer_id: i32, at: OffsetDateTime) -> Result<()> {
+        let paused = self
+            .pauses
+            .iter()
+            .any(|pause| pause.timer == timer_id && pause.end.is_none());
+        if !paused {
+            self.pauses.push(Pause {
+                timer: timer_id,
+                start: at,
+                end: None,
+            });
+        }
+        Ok(())
+    }
+
+    fn resume_timer(&mut self, timer_id: i32, at: OffsetDateTime) -> Result<()> {
+        for pause in self.pauses.iter_mut() {
+            if pause.timer == timer_id && pause.end.is_none() {
+                pause.end = Some(at);
+            }
+        }
+        Ok(())
+    }
+
+    fn update_timer_task(&mut self, timer_id: i32, task_id: i32) -> Result<()> {
+        if let Some(timer) = self.timers.iter_mut().find(|timer| timer.id == timer_id) {
+            timer.task = Some(task_id);
+            timer.status = TimerStatus::Completed;
+        }
+        Ok(())
+    }
+
+    fn set_timer_task(&mut self, timer_id: i32, task_id: Option<i32>) -> Result<()> {
+        if let Some(timer) = self.timers.iter_mut().find(|timer| timer.id == timer_id) {
+            timer.task = task_id;
+        }
+        Ok(())
+    }
+
+    fn set_timer_status(
+        &mut self,
+        timer_id: i32,
+        status: TimerStatus,
+        reason: String,
+    ) -> Result<()> {
+        if let Some(timer) = self.timers.iter_mut().find(|timer| timer.id == timer_id) {
+            timer.status = status;
+            timer.reason = reason;
+        }
+        Ok(())
+    }
+
+    fn get_timer_interruptions(&self, timer_id: i32) -> Result<Vec<Interruption>> {
+        let mut interruptions: Vec<Interruption> = self
+            .interruptions
+            .iter()
+            .filter(|interruption| interruption.timer == timer_id)
+            .cloned()
+            .collect();
+        interruptions.sort_by_key(|interruption| (interruption.at, interruption.id));
+        Ok(interruptions)
+    }
+
+    fn get_task_interruptions(&self, task_id: i32) -> Result<Vec<Interruption>> {
+        let subtree = self.subtree(task_id);
+        let mut interruptions: Vec<Interruption> = self
+            .interruptions
+            .iter()
+            .filter(|interruption| {
+                self.timers.iter().any(|timer| {
+                    timer.id == interruption.timer
+                        && timer.task.is_some_and(|task| subtree.contains(&task))
+                })
+            })
+            .cloned()
+            .collect();
+        interruptions.sort_by_key(|interruption| (interruption.at, interruption.id));
+        Ok(interruptions)
+    }
+
+    fn create_interruption(&mut self, mut interruption: Interruption) -> Result<()> {
+        self.last_interruption_id += 1;
+        interruption.id = self.last_interruption_id;
+        self.interruptions.push(interruption);
+        Ok(())
+    }
+
+    fn get_queue(&self) -> Result<Vec<Timer>> {
+        Ok(self.queue.clone())
+    }
+
+    fn save_queue(&mut self, queue: &[Timer]) -> Result<()> {
+        self.queue = queue.to_vec();
+        Ok(())
+    }
+
+    fn get_task_pomodoros(&self, task_id: i32) -> Result<usize> {
+        let subtree = self.subtree(task_id);
+        Ok(self
+            .timers
+            .iter()
+            .filter(|timer| timer.status == TimerStatus::Completed)
+            .filter(|timer| timer.task.is_some_and(|task| subtree.contains(&task)))
+            .count())
+    }
+
+    fn get_task_estimate(&self, task_id: i32) -> Result<i32> {
+        let subtree = self.subtree(task_id);
+        Ok(self
+            .tasks
+            .iter()
+            .filter(|task| subtree.contains(&task.id))
+            .map(|task| task.estimate)
+            .sum())
+    }
+
+    fn get_pomodoros_median(&self) -> Result<i32> {
+        // Mirrors the SQL in SqliteStore, which compares dates and times in UTC.
+        let now = OffsetDateTime::now_utc();
+        let today = now.date();
+        let since = today - Duration::days(30);
+        let mut per_day: BTreeMap<_, i32> = BTreeMap::new();
+        for timer in self
+            .timers
+            .iter()
+            .filter(|timer| timer.is_pomodoro() && timer.status == TimerStatus::Completed)
+        {
+            let start = timer.start.to_offset(UtcOffset::UTC);
+            if start.date() >= since && start.date() < today && start.time() >= now.time() {
+                *per_day.entry(start.date()).or_default() += 1;
+            }
+        }
+        let mut pomodoros: Vec<i32> = per_day.into_values().collect();
+        if pomodoros.is_empty() {
+            Ok(0)
+        } else {
+            Ok(median(&mut pomodoros))
+        }
+    }
+
+    fn get_statistics(&self, since: Date) -> Result<Statistics> {
+        let mut days: BTreeMap<String, i32> = BTreeMap::new();
+        let mut weeks: BTreeMap<String, i32> = BTreeMap::new();
+        let mut months: BTreeMap<String, i32> = BTreeMap::new();
+        let mut tasks: BTreeMap<i32, TaskTotal> = BTreeMap::new();
+        let mut tags: BTreeMap<String, TaskTotal> = BTreeMap::new();
+        let mut statistics = Statistics::default();
+        for timer in self.completed_pomodoros(since) {
+            let date = timer.start.date();
+            *days.entry(date.to_string()).or_default() += 1;
+            *weeks
+                .entry(format!("{}-W{:02}", date.year(), date.monday_based_week()))
+                .or_default() += 1;
+            *months
+                .entry(format!("{}-{:02}", date.year(), date.month() as u8))
+                .or_default() += 1;
+            statistics.focus_minutes += i64::from(timer.duration);
+            if let Some(task) = self.tasks.iter().find(|task| Some(task.id) == timer.task) {
+                let total = tasks.entry(task.id).or_insert(TaskTotal {
+                    name: task.name.clone(),
+                    pomodoros: 0,
+                    minutes: 0,
+                });
+                total.pomodoros += 1;
+                total.minutes += i64::from(timer.duration);
+                for tag in task.tags.iter() {
+                    let total = tags.entry(tag.clone()).or_insert(TaskTotal {
+                        name: tag.clone(),
+                        pomodoros: 0,
+                        minutes: 0,
+                    });
+                    total.pomodoros += 1;
+                    total.minutes += i64::from(timer.duration);
+                }
+            }
+        }
+        for interruption in self.interruptions.iter() {
+            let counted = self
+                .completed_pomodoros(since)
+                .any(|timer| timer.id == interruption.timer);
+            match interruption.kind {
+                InterruptionKind::Internal if counted => statistics.internal_interruptions += 1,
+                InterruptionKind::External if counted => statistics.external_interruptions += 1,
+                _ => {}
+            }
+        }
+        statistics.stopped = self
+            .timers
+            .iter()
+            .filter(|timer| {
+                timer.is_pomodoro()
+                    && matches!(timer.status, TimerStatus::Voided | TimerStatus::Abandoned)
+                    && timer.start.date() >= since
+            })
+            .rev()
+            .map(|timer| self.with_pauses(timer))
+            .collect();
+        statistics.break_minutes = self
+            .timers
+            .iter()
+            .filter(|timer| !timer.is_pomodoro() && timer.status == TimerStatus::Completed)
+            .filter(|timer| timer.start.date() >= since)
+            .map(|timer| i64::from(timer.duration))
+            .sum();
+        let recent: Vec<Timer> = self
+            .timers
+            .iter()
+            .filter(|timer| timer.start.date() >= since)
+            .cloned()
+            .collect();
+        statistics.rest = rest_by_week(&recent);
+        statistics.days = days.into_iter().collect();
+        statistics.weeks = weeks.into_iter().collect();
+        statistics.months = months.into_iter().collect();
+        statistics.tasks = tasks.into_values().collect();
+        statistics.tags = tags.into_values().collect();
+        for totals in [&mut statistics.tasks, &mut statistics.tags] {
+            totals.sort_by(|a, b| {
+                b.pomodoros
+                    .cmp(&a.pomodoros)
+                    .then_with(|| a.name.cmp(&b.name))
+            });
+        }
+        Ok(statistics)
+    }
+
+    fn get_estimate_history(&self) -> Result<Vec<EstimateRecord>> {
+        let mut records: Vec<EstimateRecord> = vec![];
+        for task in self
+            .tasks
+            .iter()
+            .filter(|task| task.done && task.estimate > 0)
+        {
+            let pomodoros: Vec<&Timer> = self
+                .timers
+                .iter()
+                .filter(|timer| timer.is_pomodoro() && timer.status == TimerStatus::Completed)
+                .filter(|timer| timer.task == Some(task.id))
+                .collect();
+            records.push(EstimateRecord {
+                name: task.name.clone(),
+                estimate: task.estimate,
+                actual: pomodoros.len() as i32,
+                finished: task
+                    .completed_at
+                    .map(|completed_at| completed_at.date())
+                    .or(pomodoros.iter().map(|timer| timer.start.date()).max())
+                    .map(|date| date.to_string()),
+            });
+        }
+        records.sort_by(|a, b| a.finished.cmp(&b.finished));
+        Ok(records)
+    }
+}
@@ -0,0 +1,113 @@
+use crate::error::Result;
+use crate::model::{Interruption, Project, Task, Timer, TimerStatus};
+use crate::recurrence::Recurrence;
+use crate::stats::{EstimateRecord, Statistics};
+use time::{Date, OffsetDateTime};
+
+mod memory;
+mod sqlite;
+
+pub use memory::InMemoryStore;
+pub use sqlite::SqliteStore;
+
+/// Shared by the GUI and `pomo`, relative to the working directory.
+pub const DATABASE_PATH: &str = "tasks.db";
+
+/// Persistence for tasks and timers. `SqliteStore` backs the app with
+/// `tasks.db`, `InMemoryStore` keeps everything in memory for tests and as a
+/// fallback when the database can't be opened.
+pub trait Store {
+    /// Open tasks in list order: by priority, then by position.
+    fn get_tasks(&self) -> Result<Vec<Task>>;
+    /// Done tasks completed between `from` and `to` (local dates, inclusive),
+    /// most recent first. Tasks without a completion time only show up when
+    /// there is no `from`.
+    fn get_done_tasks(&self, from: Option<Date>, to: Option<Date>) -> Result<Vec<Task>>;
+    /// Open and done tasks alike, by id.
+    fn get_all_tasks(&self) -> Result<Vec<Task>>;
+    /// Adds a task under a new id, which is returned.
+    fn create_task(&mut self, task: Task) -> Result<i32>;
+    /// Ticks a task off, recording when, or reopens it. Ticking off a
+    /// repeating task adds its next occurrence, see `Task::next_occurrence`.
+    /// A task ticked off is taken off running timers, so they aren't
+    /// credited to it.
+    fn set_task_status(&mut self, done: bool, id: i32) -> Result<()>;
+    fn set_task_locked(&mut self, locked: bool, id: i32) -> Result<()>;
+    fn set_task_just_created(&mut self, just_created: bool, id: i32) -> Result<()>;
+    fn set_task_name(&mut self, name: String, id: i32) -> Result<()>;
+    fn set_task_estimate(&mut self, estimate: i32, id: i32) -> Result<()>;
+    fn set_task_due(&mut self, due: Option<Date>, id: i32) -> Result<()>;
+    fn set_task_planned(&mut self, planned: Option<Date>, id: i32) -> Result<()>;
+    fn set_task_recurrence(&mut self, recurrence: Option<Recurrence>, id: i32) -> Result<()>;
+    /// Replaces the tags of a task, which are kept sorted.
+    fn set_task_tags(&mut self, tags: Vec<String>, id: i32) -> Result<()>;
+    fn set_task_priority(&mut self, priority: u8, id: i32) -> Result<()>;
+    fn set_task_notes(&mut self, notes: String, id: i32) -> Result<()>;
+    /// Deletes a task, taking it off running timers.
+    fn delete_task(&mut self, id: i32) -> Result<()>;
+    /// Writes every field of `task`, recreating it under its id if it was
+    /// deleted. Used to restore earlier states.
+    fn put_task(&mut self, task: &Task) -> Result<()>;
+    /// Reorders the tasks in `ids` among the positions they already take up,
+    /// so the first id gets the topmost of them. New tasks go to the bottom.
+    fn set_task_order(&mut self, ids: &[i32]) -> Result<()>;
+    /// Moves a task and its subtasks to a project, or to the inbox with `None`.
+    fn set_task_project(&mut self, project: Option<i32>, id: i32) -> Result<()>;
+
+    /// Projects by name.
+    fn get_projects(&self) -> Result<Vec<Project>>;
+    fn create_project(&mut self, name: String) -> Result<()>;
+    /// Pomodoros credited to tasks of a project, or of the inbox with `None`.
+    fn get_project_pomodoros(&self, project: Option<i32>) -> Result<usize>;
+
+    /// Timers neither credited to a task nor stopped, i.e. the one
+    /// currently running.
+    fn get_running_timers(&self) -> Result<Vec<Timer>>;
+    /// Every stored timer, credited or not, by id.
+    fn get_all_timers(&self) -> Result<Vec<Timer>>;
+    fn get_timer(&self, id: i32) -> Result<Option<Timer>>;
+    fn create_timer(&mut self, timer: Timer) -> Result<()>;
+    /// Writes every field of `timer` like `put_task`. Its pauses are stored
+    /// as a single finished pause plus the one in progress, which adds up to
+    /// the same remaining time.
+    fn put_timer(&mut self, timer: &Timer) -> Result<()>;
+    /// Deletes a timer along with its pauses and interruptions.
+    fn delete_timer(&mut self, id: i32) -> Result<()>;
+    fn pause_timer(&mut self, timer_id: i32, at: OffsetDateTime) -> Result<()>;
+    fn resume_timer(&mut self, timer_id: i32, at: OffsetDateTime) -> Result<()>;
+    /// Credits a timer to a task, which completes it.
+    fn update_timer_task(&mut self, timer_id: i32, task_id: i32) -> Result<()>;
+    /// Sets the task a running timer is for without completing it, `None`
+    /// to clear it.
+    fn set_timer_task(&mut self, timer_id: i32, task_id: Option<i32>) -> Result<()>;
+    /// Ends a timer without crediting it, keeping it as a record: a break
+    /// that ran out, or a timer stopped early.
+    fn set_timer_status(
+        &mut self,
+        timer_id: i32,
+        status: TimerStatus,
+        reason: String,
+    ) -> Result<()>;
+    /// Interruptions of a timer, oldest first.
+    fn get_timer_interruptions(&self, timer_id: i32) -> Result<Vec<Interruption>>;
+    /// Interruptions during pomodoros credited to a task and all of its
+    /// subtasks, oldest first.
+    fn get_task_interruptions(&self, task_id: i32) -> Result<Vec<Interruption>>;
+    fn create_interruption(&mut self, interruption: Interruption) -> Result<()>;
+    /// Timers waiting to run, first to start first.
+    fn get_queue(&self) -> Result<Vec<Timer>>;
+    fn save_queue(&mut self, queue: &[Timer]) -> Result<()>;
+    /// Completed pomodoros of a task and all of its subtasks.
+    fn get_task_pomodoros(&self, task_id: i32) -> Result<usize>;
+    /// Estimate of a task plus those of all of its subtasks.
+    fn get_task_estimate(&self, task_id: i32) -> Result<i32>;
+    /// Median number of pomodoros completed by this time of day over the last
+    /// 30 days.
+    fn get_pomodoros_median(&self) -> Result<i32>;
+    /// Pomodoro and break counts and minutes for timers started on or after
+    /// `since`.
+    fn get_statistics(&self, since: Date) -> Result<Statistics>;
+    /// Estimated vs. actual pomodoros of done tasks that had an estimate,
+    /// oldest first.
+    fn get_estimate_history(&self) -> Result<Vec<EstimateRecord>>;
+}
@@ -0,0 +1,1177 @@
+use super::Store;
+use crate::error::Result;
+use crate::migrations::migrate;
+use crate::model::{
+    now, Interruption, InterruptionKind, Project, Task, Timer, TimerKind, TimerStatus,
+};
+use crate::recurrence::Recurrence;
+use crate::stats::{median, rest_by_week, EstimateRecord, Statistics, TaskTotal};
+use rusqlite::{Connection, OptionalExtension, Row};
+use std::path::Path;
+use time::{Date, Duration, OffsetDateTime};
+
+/// Ids of task `?1` and its subtasks at any depth, as the `subtree` table.
+const SUBTREE: &str = "WITH RECURSIVE subtree(id) AS (
+        SELECT ?1 UNION SELECT tasks.id FROM tasks JOIN subtree ON tasks.parent = subtree.id
+    )";
+
+/// Every column of `tasks`, then its tags joined by spaces, as read by
+/// `task_from_row`.
+const SELECT_TASKS: &str = "SELECT tasks.*,
+        (SELECT group_concat(tag, ' ') FROM
+            (SELECT tag FROM task_tags where task_tags.task = tasks.id ORDER BY tag))
+    FROM tasks";
+
+pub struct SqliteStore {
+    conn: Connection,
+}
+
+impl SqliteStore {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::new(Connection::open(path)?)
+    }
+
+    pub fn new(mut conn: Connection) -> Result<Self> {
+        migrate(&mut conn)?;
+        Ok(SqliteStore { conn })
+    }
+
+    fn count_pomodoros_by(&self, period: &str, since: &str) -> Result<Vec<(String, i32)>> {
+        let mut counts: Vec<(String, i32)> = vec![];
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {} AS period, count(*) FROM timers
+             where is_pomodoro = 1 and status = 'completed' and substr(start, 1, 10) >= ?1
+             GROUP BY period ORDER BY period",
+            period
+        ))?;
+        let counts_iter = stmt.query_map([since], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        for count in counts_iter {
+            counts.push(count?);
+        }
+        Ok(counts)
+    }
+
+    fn load_pauses(&self, timer: &mut Timer) -> Result<()> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT start, end FROM pauses where timer = ?1")?;
+        let pauses_iter = stmt.query_map([timer.id], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        for pause in pauses_iter {
+            let (start, end) = pause?;
+            timer.add_pause(start, end);
+        }
+        Ok(())
+    }
+}
+
+fn task_from_row(row: &Row) -> rusqlite::Result<Task> {
+    Ok(Task {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        done: row.get(2)?,
+        estimate: row.get(3)?,
+        locked: row.get(4)?,
+        just_created: row.get(5)?,
+        project: row.get(6)?,
+        parent: row.get(7)?,
+        completed_at: row.get(9)?,
+        position: row.get(8)?,
+        due: row.get(10)?,
+        planned: row.get(11)?,
+        recurrence: row
+            .get::<_, Option<String>>(12)?
+            .and_then(|rule| Recurrence::parse(&rule)),
+        priority: row.get(13)?,
+        notes: row.get(14)?,
+        tags: row
+            .get::<_, Option<String>>(15)?
+            .map(|tags| tags.split(' ').map(String::from).collect())
+            .unwrap_or_default(),
+    })
+}
+
+/// Replaces the tags of task `id`.
+fn write_tags(conn: &Connection, id: i64, tags: &[String]) -> rusqlite::Result<()> {
+    conn.execute("DELETE FROM task_tags where task = ?1", [id])?;
+    for tag in tags {
+        conn.execute(
+            "INSERT OR IGNORE INTO task_tags (task, tag) VALUES (?1, ?2)",
+            (id, tag),
+        )?;
+    }
+    Ok(())
+}
+
+/// Adds `task` under a new id, at the bottom of the list. Returns the id.
+fn insert_task(conn: &Connection, task: &Task) -> rusqlite::Result<i32> {
+    conn.execute(
+        "INSERT INTO tasks (name, done, estimate, locked, just_created, project, parent, completed_at, due, planned, recurrence, priority, notes, position)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, (SELECT coalesce(max(position), 0) + 1 FROM tasks))",
+        (
+            &task.name,
+            task.done,
+            task.estimate,
+            task.locked,
+            task.just_created,
+            task.project,
+            task.parent,
+            task.completed_at,
+            task.due,
+            task.planned,
+            task.recurrence.as_ref().map(Recurrence::to_string),
+            task.priority,
+            &task.notes,
+        ),
+    )?;
+    let id = conn.last_insert_rowid();
+    write_tags(conn, id, &task.tags)?;
+    Ok(id as i32)
+}
+
+/// Takes a task that is done or deleted off running timers.
+fn release_task(conn: &Connection, id: i32) -> rusqlite::Result<()> {
+    conn.execute(
+        "UPDATE timers SET task = NULL where task = ?1 and status = 'running'",
+        [id],
+    )?;
+    Ok(())
+}
+
+fn timer_from_row(row: &Row) -> rusqlite::Result<Timer> {
+    Ok(Timer {
+        id: row.get(0)?,
+        kind: TimerKind::from_name(&row.get::<_, String>(7)?).unwrap_or(TimerKind::Focus),
+        start: row.get(2)?,
+        duration: row.get(3)?,
+        task: row.get(4)?,
+        paused: Duration::ZERO,
+        paused_at: None,
+        status: TimerStatus::from_name(&row.get::<_, String>(5)?).unwrap_or(TimerStatus::Running),
+        reason: row.get(6)?,
+    })
+}
+
+fn interruption_from_row(row: &Row) -> rusqlite::Result<Interruption> {
+    Ok(Interruption {
+        id: row.get(0)?,
+        timer: row.get(1)?,
+        kind: if row.get(2)? {
+            InterruptionKind::Internal
+        } else {
+            InterruptionKind::External
+        },
+        at: row.get(3)?,
+        note: row.get(4)?,
+    })
+}
+
+impl Store for SqliteStore {
+    fn get_tasks(&self) -> Result<Vec<Task>> {
+        let mut tasks: Vec<Task> = vec![];
+        let mut stmt = self.conn.prepare(&format!(
+            "{} where done = 0 ORDER BY priority, position, id",
+            SELECT_TASKS
+        ))?;
+        let tasks_iter = stmt.query_map([], task_from_row)?;
+        for task in tasks_iter {
+            tasks.push(task?);
+        }
+        Ok(tasks)
+    }
+
+    fn get_done_tasks(&self, from: Option<Date>, to: Option<Date>) -> Result<Vec<Task>> {
+        let mut tasks: Vec<Task> = vec![];
+        let mut stmt = self.conn.prepare(&format!(
+            "{} where done = 1
+             and (?1 is NULL or substr(completed_at, 1, 10) >= ?1)
+             and (?2 is NULL or substr(completed_at, 1, 10) <= ?2)
+             ORDER BY completed_at is NULL, completed_at DESC, id DESC",
+            SELECT_TASKS
+        ))?;
+        let tasks_iter = stmt.query_map(
+            [
+                from.map(|date| date.to_string()),
+                to.map(|date| date.to_string()),
+            ],
+            task_from_row,
+        )?;
+        for task in tasks_iter {
+            tasks.push(task?);
+        }
+        Ok(tasks)
+    }
+
+    fn get_all_tasks(&self) -> Result<Vec<Task>> {
+        let mut tasks: Vec<Task> = vec![];
+        let mut stmt = self
+            .conn
+            .prepare(&format!("{} ORDER BY id", SELECT_TASKS))?;
+        let tasks_iter = stmt.query_map([], task_from_row)?;
+        for task in tasks_iter {
+            tasks.push(task?);
+        }
+        Ok(tasks)
+    }
+
+    fn create_task(&mut self, task: Task) -> Result<i32> {
+        let tx = self.conn.transaction()?;
+        let id = insert_task(&tx, &task)?;
+        tx.commit()?;
+        Ok(id)
+    }
+
+    fn set_task_status(&mut self, done: bool, id: i32) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        let task = tx
+            .query_row(
+                &format!("{} where id = ?1", SELECT_TASKS),
+                [id],
+                task_from_row,
+            )
+            .optional()?;
+        let completed_at = if done { Some(now()) } else { None };
+        tx.execute(
+            "UPDATE tasks SET done = ?1, completed_at = ?2 where id = ?3",
+            (done, completed_at, id),
+        )?;
+        // Ticking off a repeating task hands the rule on to its next occurrence.
+        if let Some(next) = task
+            .filter(|task| done && !task.done)
+            .and_then(|task| task.next_occurrence(now().date()))
+        {
+            insert_task(&tx, &next)?;
+            tx.execute("UPDATE tasks SET recurrence = NULL where id = ?1", [id])?;
+        }
+        if done {
+            release_task(&tx, id)?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn set_task_locked(&mut self, locked: bool, id: i32) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        tx.execute("UPDATE tasks SET locked = ?1 where id = ?2", (locked, id))?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn set_task_just_created(&mut self, just_created: bool, id: i32) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        tx.execute(
+            "UPDATE tasks SET just_created = ?1 where id = ?2",
+            (just_created, id),
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn set_task_name(&mut self, name: String, id: i32) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        tx.execute("UPDATE tasks SET name = ?1 where id = ?2", (name, id))?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn set_task_estimate(&mut self, estimate: i32, id: i32) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        tx.execute(
+            "UPDATE tasks SET estimate = ?1 where id = ?2",
+            (estimate, id),
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn set_task_due(&mut self, due: Option<Date>, id: i32) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        tx.execute("UPDATE tasks SET due = ?1 where id = ?2", (due, id))?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn set_task_planned(&mut self, planned: Option<Date>, id: i32) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        tx.execute("UPDATE tasks SET planned = ?1 where id = ?2", (planned, id))?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn set_task_recurrence(&mut self, recurrence: Option<Recurrence>, id: i32) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        tx.execute(
+            "UPDATE tasks SET recurrence = ?1 where id = ?2",
+            (recurrence.as_ref().map(Recurrence::to_string), id),
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn set_task_tags(&mut self, tags: Vec<String>, id: i32) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        write_tags(&tx, id.into(), &tags)?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn set_task_priority(&mut self, priority: u8, id: i32) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        tx.execute(
+            "UPDATE tasks SET priority = ?1 where id = ?2",
+            (priority, id),
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn set_task_notes(&mut self, notes: String, id: i32) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        tx.execute("UPDATE tasks SET notes = ?1 where id = ?2", (notes, id))?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn delete_task(&mut self, id: i32) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        release_task(&tx, id)?;
+        tx.execute("DELETE from task_tags where task = ?1", [id])?;
+        tx.execute("DELETE from tasks where id = ?1", [id])?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn put_task(&mut self, task: &Task) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        tx.execute(
+            "INSERT OR REPLACE INTO tasks
+             (id, name, done, estimate, locked, just_created, project, parent, position, completed_at, due, planned, recurrence, priority, notes)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15)",
+            (
+                task.id,
+                &task.name,
+                task.done,
+                task.estimate,
+                task.locked,
+                task.just_created,
+                task.project,
+                task.parent,
+                task.position,
+                task.completed_at,
+                task.due,
+                task.planned,
+                task.recurrence.as_ref().map(Recurrence::to_string),
+                task.priority,
+                &task.notes,
+            ),
+        )?;
+        write_tags(&tx, task.id.into(), &task.tags)?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn set_task_order(&mut self, ids: &[i32]) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        let mut positions: Vec<i32> = vec![];
+        for id in ids {
+            positions.push(tx.query_row(
+                "SELECT position FROM tasks where id = ?1",
+                [id],
+                |row| row.get(0),
+            )?);
+        }
+        positions.sort();
+        for (id, position) in ids.iter().zip(positions) {
+            tx.execute(
+                "UPDATE tasks SET position = ?1 where id = ?2",
+                (position, id),
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn set_task_project(&mut self, project: Option<i32>, id: i32) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        tx.execute(
+            &format!(
+                "{} UPDATE tasks SET project = ?2 where id in subtree",
+                SUBTREE
+            ),
+            (id, project),
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn get_projects(&self) -> Result<Vec<Project>> {
+        let mut projects: Vec<Project> = vec![];
+        let mut stmt = self
+            .conn
+            .prepare("SELECT id, name FROM projects ORDER BY name")?;
+        let projects_iter = stmt.query_map([], |row| {
+            Ok(Project {
+                id: row.get(0)?,
+                name: row.get(1)?,
+            })
+        })?;
+        for project in projects_iter {
+            projects.push(project?);
+        }
+        Ok(projects)
+    }
+
+    fn create_project(&mut self, name: String) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        tx.execute("INSERT INTO projects (name) VALUES (?1)", [name])?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn get_project_pomodoros(&self, project: Option<i32>) -> Result<usize> {
+        let pomodoros: i64 = self.conn.query_row(
+            "SELECT count(*) FROM timers JOIN tasks ON timers.task = tasks.id
+             where timers.is_pomodoro = 1 and timers.status = 'completed'
+                and tasks.project IS ?1",
+            [project],
+            |row| row.get(0),
+        )?;
+        Ok(pomodoros as usize)
+    }
+
+    fn get_running_timers(&self) -> Result<Vec<Timer>> {
+        let mut timers: Vec<Timer> = vec![];
+        let mut stmt = self
+            .conn
+            .prepare("SELECT * FROM timers where status = 'running'")?;
+        let timers_iter = stmt.query_map([], timer_from_row)?;
+        for timer in timers_iter {
+            let mut timer = timer?;
+            self.load_pauses(&mut timer)?;
+            timers.push(timer);
+        }
+        Ok(timers)
+    }
+
+    fn get_all_timers(&self) -> Result<Vec<Timer>> {
+        let mut timers: Vec<Timer> = vec![];
+        let mut stmt = self.conn.prepare("SELECT * FROM timers ORDER BY id")?;
+        let timers_iter = stmt.query_map([], timer_from_row)?;
+        for timer in timers_iter {
+            let mut timer = timer?;
+            self.load_pauses(&mut timer)?;
+            timers.push(timer);
+        }
+        Ok(timers)
+    }
+
+    fn get_timer(&self, id: i32) -> Result<Option<Timer>> {
+        let mut stmt = self.conn.prepare("SELECT * FROM timers where id = ?1")?;
+        let mut timers_iter = stmt.query_map([id], timer_from_row)?;
+        match timers_iter.next() {
+            Some(timer) => {
+                let mut timer = timer?;
+                self.load_pauses(&mut timer)?;
+                Ok(Some(timer))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn create_timer(&mut self, timer: Timer) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        tx.execute(
+            "INSERT INTO timers (is_pomodoro, start, duration, task, status, reason, kind)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            (
+                timer.is_pomodoro(),
+                timer.start,
+                timer.duration,
+                timer.task,
+                timer.status.name(),
+                &timer.reason,
+                timer.kind.name(),
+            ),
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn put_timer(&mut self, timer: &Timer) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        tx.execute(
+            "INSERT OR REPLACE INTO timers (id, is_pomodoro, start, duration, task, status, reason, kind)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            (
+                timer.id,
+                timer.is_pomodoro(),
+                timer.start,
+                timer.duration,
+                timer.task,
+                timer.status.name(),
+                &timer.reason,
+                timer.kind.name(),
+            ),
+        )?;
+        tx.execute("DELETE FROM pauses where timer = ?1", [timer.id])?;
+        if timer.paused > Duration::ZERO {
+            tx.execute(
+                "INSERT INTO pauses (timer, start, end) VALUES (?1, ?2, ?3)",
+                (timer.id, timer.start, timer.start + timer.paused),
+            )?;
+        }
+        if let Some(paused_at) = timer.paused_at {
+            tx.execute(
+                "INSERT INTO pauses (timer, start) VALUES (?1, ?2)",
+                (timer.id, paused_at),
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn delete_timer(&mut self, id: i32) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        tx.execute("DELETE FROM pauses where timer = ?1", [id])?;
+        tx.execute("DELETE FROM interruptions where timer = ?1", [id])?;
+        tx.execute("DELETE FROM timers where id = ?1", [id])?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn pause_timer(&mut self, timer_id: i32, at: OffsetDateTime) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        tx.execute(
+            "INSERT INTO pauses (timer, start) SELECT ?1, ?2
+             WHERE NOT EXISTS (SELECT 1 FROM pauses where timer = ?1 and end is NULL)",
+            (timer_id, at),
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn resume_timer(&mut self, timer_id: i32, at: OffsetDateTime) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        tx.execute(
+            "UPDATE pauses SET end = ?1 where timer = ?2 and end is NULL",
+            (at, timer_id),
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn update_timer_task(&mut self, timer_id: i32, task_id: i32) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        tx.execute(
+            "UPDATE timers SET task = ?1, status = 'completed' where id = ?2",
+            (task_id, timer_id),
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn set_timer_task(&mut self, timer_id: i32, task_id: Option<i32>) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        tx.execute(
+            "UPDATE timers SET task = ?1 where id = ?2",
+            (task_id, timer_id),
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn set_timer_status(
+        &mut self,
+        timer_id: i32,
+        status: TimerStatus,
+        reason: String,
+    ) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        tx.execute(
+            "UPDATE timers SET status = ?1, reason = ?2 where id = ?3",
+            (status.name(), reason, timer_id),
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn get_timer_interruptions(&self, timer_id: i32) -> Result<Vec<Interruption>> {
+        let mut interruptions: Vec<Interruption> = vec![];
+        let mut stmt = self.conn.prepare(
+            "SELECT id, timer, is_internal, at, note FROM interruptions
+             where timer = ?1 ORDER BY at, id",
+        )?;
+        let interruptions_iter = stmt.query_map([timer_id], interruption_from_row)?;
+        for interruption in interruptions_iter {
+            interruptions.push(interruption?);
+        }
+        Ok(interruptions)
+    }
+
+    fn get_task_interruptions(&self, task_id: i32) -> Result<Vec<Interruption>> {
+        let mut interruptions: Vec<Interruption> = vec![];
+        let mut stmt = self.conn.prepare(&format!(
+            "{} SELECT interruptions.id, timer, is_internal, at, note
+             FROM interruptions JOIN timers ON interruptions.timer = timers.id
+             where timers.task in subtree ORDER BY at, interruptions.id",
+            SUBTREE
+        ))?;
+        let interruptions_iter = stmt.query_map([task_id], interruption_from_row)?;
+        for interruption in interruptions_iter {
+            interruptions.push(interruption?);
+        }
+        Ok(interruptions)
+    }
+
+    fn create_interruption(&mut self, interruption: Interruption) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        tx.execute(
+            "INSERT INTO interruptions (timer, is_internal, at, note) VALUES (?1, ?2, ?3, ?4)",
+            (
+                interruption.timer,
+                interruption.kind == InterruptionKind::Internal,
+                interruption.at,
+                &interruption.note,
+            ),
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn get_queue(&self) -> Result<Vec<Timer>> {
+        let mut queue: Vec<Timer> = vec![];
+        let mut stmt = self
+            .conn
+            .prepare("SELECT kind, duration FROM queue ORDER BY position")?;
+        let queue_iter = stmt.query_map([], |row| {
+            let kind = TimerKind::from_name(&row.get::<_, String>(0)?).unwrap_or(TimerKind::Focus);
+            Ok(Timer::new(kind, row.get(1)?, now()))
+        })?;
+        for timer in queue_iter {
+            queue.push(timer?);
+        }
+        Ok(queue)
+    }
+
+    fn save_queue(&mut self, queue: &[Timer]) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        tx.execute("DELETE from queue", [])?;
+        for (position, timer) in queue.iter().enumerate() {
+            tx.execute(
+                "INSERT INTO queue (position, is_pomodoro, duration, kind) VALUES (?1, ?2, ?3, ?4)",
+                (
+                    position,
+                    timer.is_pomodoro(),
+                    timer.duration,
+                    timer.kind.name(),
+                ),
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn get_task_pomodoros(&self, task_id: i32) -> Result<usize> {
+        let pomodoros: i64 = self.conn.query_row(
+            &format!(
+                "{} SELECT count(*) FROM timers where task in subtree and status = 'completed'",
+                SUBTREE
+            ),
+            [task_id],
+            |row| row.get(0),
+        )?;
+        Ok(pomodoros as usize)
+    }
+
+    fn get_task_estimate(&self, task_id: i32) -> Result<i32> {
+        Ok(self.conn.query_row(
+            &format!(
+                "{} SELECT coalesce(sum(estimate), 0) FROM tasks where id in subtree",
+                SUBTREE
+            ),
+            [task_id],
+            |row| row.get(0),
+        )?)
+    }
+
+    fn get_pomodoros_median(&self) -> Result<i32> {
+        let mut pomodoros: Vec<i32> = vec![];
+        let mut stmt = self.conn
+            .prepare("select count(start) from timers where is_pomodoro = 1 and status = 'completed' and start >= date('now','-30 days') and start < date('now') and time(start) >= time('now') group by date(start)")
+            ?;
+        let pomodoros_iter = stmt.query_map([], |row| row.get(0))?;
+        for pomodoro_count in pomodoros_iter {
+            pomodoros.push(pomodoro_count?);
+        }
+        if pomodoros.is_empty() {
+            Ok(0)
+        } else {
+            Ok(median(&mut pomodoros))
+        }
+    }
+
+    fn get_statistics(&self, since: Date) -> Result<Statistics> {
+        // `start` is stored as "YYYY-MM-DD HH:MM:SS.f+hh:mm", so its first ten
+        // characters are the local day the timer started on.
+        let since = since.to_string();
+        let mut statistics = Statistics {
+            days: self.count_pomodoros_by("substr(start, 1, 10)", &since)?,
+            weeks: self.count_pomodoros_by("strftime('%Y-W%W', substr(start, 1, 10))", &since)?,
+            months: self.count_pomodoros_by("substr(start, 1, 7)", &since)?,
+            ..Default::default()
+        };
+        (statistics.focus_minutes, statistics.break_minutes) = self.conn.query_row(
+            "SELECT
+                coalesce(sum(CASE WHEN is_pomodoro = 1 and status = 'completed' THEN duration END), 0),
+                coalesce(sum(CASE WHEN is_pomodoro = 0 and status = 'completed' THEN duration END), 0)
+             FROM timers where substr(start, 1, 10) >= ?1",
+            [&since],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        let mut stmt = self.conn.prepare(
+            "SELECT tasks.name, count(timers.id), sum(timers.duration)
+             FROM timers JOIN tasks ON timers.task = tasks.id
+             where timers.is_pomodoro = 1 and timers.status = 'completed'
+                and substr(timers.start, 1, 10) >= ?1
+             GROUP BY tasks.id ORDER BY count(timers.id) DESC, tasks.name",
+        )?;
+        let tasks_iter = stmt.query_map([&since], |row| {
+            Ok(TaskTotal {
+                name: row.get(0)?,
+                pomodoros: row.get(1)?,
+                minutes: row.get(2)?,
+            })
+        })?;
+        for task in tasks_iter {
+            statistics.tasks.push(task?);
+        }
+        let mut stmt = self.conn.prepare(
+            "SELECT task_tags.tag, count(timers.id), sum(timers.duration)
+             FROM timers JOIN task_tags ON timers.task = task_tags.task
+             where timers.is_pomodoro = 1 and timers.status = 'completed'
+                and substr(timers.start, 1, 10) >= ?1
+             GROUP BY task_tags.tag ORDER BY count(timers.id) DESC, task_tags.tag",
+        )?;
+        let tags_iter = stmt.query_map([&since], |row| {
+            Ok(TaskTotal {
+                name: row.get(0)?,
+                pomodoros: row.get(1)?,
+                minutes: row.get(2)?,
+            })
+        })?;
+        for tag in tags_iter {
+            statistics.tags.push(tag?);
+        }
+        (
+            statistics.internal_interruptions,
+            statistics.external_interruptions,
+        ) = self.conn.query_row(
+            "SELECT
+                coalesce(sum(interruptions.is_internal), 0),
+                coalesce(sum(1 - interruptions.is_internal), 0)
+             FROM interruptions JOIN timers ON interruptions.timer = timers.id
+             where timers.is_pomodoro = 1 and timers.status = 'completed'
+                and substr(timers.start, 1, 10) >= ?1",
+            [&since],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        let mut completed: Vec<Timer> = vec![];
+        let mut stmt = self.conn.prepare(
+            "SELECT * FROM timers where status = 'completed' and substr(start, 1, 10) >= ?1",
+        )?;
+        for timer in stmt.query_map([&since], timer_from_row)? {
+            completed.push(timer?);
+        }
+        statistics.rest = rest_by_week(&completed);
+        let mut stmt = self.conn.prepare(
+            "SELECT * FROM timers where is_pomodoro = 1 and status in ('voided', 'abandoned')
+                and substr(start, 1, 10) >= ?1 ORDER BY id DESC",
+        )?;
+        let stopped_iter = stmt.query_map([&since], timer_from_row)?;
+        for timer in stopped_iter {
+            let mut timer = timer?;
+            self.load_pauses(&mut timer)?;
+            statistics.stopped.push(timer);
+        }
+        Ok(statistics)
+    }
+
+    fn get_estimate_history(&self) -> Result<Vec<EstimateRecord>> {
+        let mut records: Vec<EstimateRecord> = vec![];
+        let mut stmt = self.conn.prepare(
+            "SELECT tasks.name, tasks.estimate, count(timers.id),
+                coalesce(substr(tasks.completed_at, 1, 10), max(substr(timers.start, 1, 10))) AS finished
+             FROM tasks LEFT JOIN timers ON timers.task = tasks.id and timers.is_pomodoro = 1
+                and timers.status = 'completed'
+             where tasks.done = 1 and tasks.estimate > 0
+             GROUP BY tasks.id ORDER BY finished, tasks.id",
+        )?;
+        let records_iter = stmt.query_map([], |row| {
+            Ok(EstimateRecord {
+                name: row.get(0)?,
+                estimate: row.get(1)?,
+                actual: row.get(2)?,
+                finished: row.get(3)?,
+            })
+        })?;
+        for record in records_iter {
+            records.push(record?);
+        }
+        Ok(records)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::macros::{date, datetime};
+
+    fn pomodoro(start: OffsetDateTime, task: Option<i32>) -> Timer {
+        Timer {
+            task,
+            status: if task.is_some() {
+                TimerStatus::Completed
+            } else {
+                TimerStatus::Running
+            },
+            ..Timer::new(TimerKind::Focus, 25, start)
+        }
+    }
+
+    #[test]
+    fn statistics_group_credited_pomodoros_by_local_day() {
+        let mut store = SqliteStore::new(Connection::open_in_memory().unwrap()).unwrap();
+        store
+            .create_task(Task {
+                estimate: 2,
+                ..Task::new("write report")
+            })
+            .unwrap();
+        // Late evening in UTC+2 is still the same local day.
+        store
+            .create_timer(pomodoro(datetime!(2024-05-01 23:30 +2), Some(1)))
+            .unwrap();
+        store
+            .create_timer(pomodoro(datetime!(2024-05-01 09:00 +2), Some(1)))
+            .unwrap();
+        store
+            .create_timer(pomodoro(datetime!(2024-05-02 09:00 +2), None))
+            .unwrap();
+
+        let statistics = store.get_statistics(date!(2024 - 01 - 01)).unwrap();
+
+        assert_eq!(statistics.days, vec![("2024-05-01".to_string(), 2)]);
+        assert_eq!(statistics.weeks, vec![("2024-W18".to_string(), 2)]);
+        assert_eq!(statistics.months, vec![("2024-05".to_string(), 2)]);
+        assert_eq!(statistics.focus_minutes, 50);
+        assert_eq!(statistics.tasks.len(), 1);
+        assert_eq!(statistics.tasks[0].pomodoros, 2);
+    }
+
+    #[test]
+    fn tags_are_stored_and_totalled() {
+        let mut store = SqliteStore::new(Connection::open_in_memory().unwrap()).unwrap();
+        for tags in [vec!["deep", "writing"], vec!["writing"]] {
+            store
+                .create_task(Task {
+                    tags: tags.into_iter().map(String::from).collect(),
+                    ..Task::new("write report")
+                })
+                .unwrap();
+        }
+        for task in [1, 2] {
+            store
+                .create_timer(pomodoro(datetime!(2024-05-01 09:00 +2), Some(task)))
+                .unwrap();
+        }
+        assert_eq!(store.get_tasks().unwrap()[0].tags, ["deep", "writing"]);
+
+        let totals: Vec<(String, i32)> = store
+            .get_statistics(date!(2024 - 01 - 01))
+            .unwrap()
+            .tags
+            .into_iter()
+            .map(|tag| (tag.name, tag.pomodoros))
+            .collect();
+        assert_eq!(
+            totals,
+            [("writing".to_string(), 2), ("deep".to_string(), 1)]
+        );
+
+        store.set_task_tags(vec![], 1).unwrap();
+        assert!(store.get_tasks().unwrap()[0].tags.is_empty());
+        let task = Task {
+            tags: vec!["again".to_string()],
+            ..store.get_all_tasks().unwrap()[0].clone()
+        };
+        store.put_task(&task).unwrap();
+        assert_eq!(store.get_all_tasks().unwrap()[0], task);
+    }
+
+    #[test]
+    fn tasks_are_sorted_by_priority_then_position() {
+        let mut store = SqliteStore::new(Connection::open_in_memory().unwrap()).unwrap();
+        for (name, priority) in [("later", 4), ("urgent", 1), ("soon", 2), ("also urgent", 1)] {
+            store
+                .create_task(Task {
+                    priority,
+                    ..Task::new(name)
+                })
+                .unwrap();
+        }
+        store.set_task_priority(3, 3).unwrap();
+        let names: Vec<String> = store
+            .get_tasks()
+            .unwrap()
+            .into_iter()
+            .map(|task| task.name)
+            .collect();
+        assert_eq!(names, ["urgent", "also urgent", "soon", "later"]);
+    }
+
+    #[test]
+    fn moving_a_task_moves_its_pomodoros_between_projects() {
+        let mut store = SqliteStore::new(Connection::open_in_memory().unwrap()).unwrap();
+        store.create_project("work".to_string()).unwrap();
+        store
+            .create_task(Task {
+                estimate: 2,
+                ..Task::new("write report")
+            })
+            .unwrap();
+        store
+            .create_timer(pomodoro(datetime!(2024-05-01 09:00 +2), Some(1)))
+            .unwrap();
+        assert_eq!(store.get_project_pomodoros(None).unwrap(), 1);
+
+        let work = store.get_projects().unwrap()[0].id;
+        store.set_task_project(Some(work), 1).unwrap();
+
+        assert_eq!(store.get_project_pomodoros(None).unwrap(), 0);
+        assert_eq!(store.get_project_pomodoros(Some(work)).unwrap(), 1);
+        assert_eq!(store.get_tasks().unwrap()[0].project, Some(work));
+    }
+
+    #[test]
+    fn subtasks_roll_up_into_their_parents() {
+        let mut store = SqliteStore::new(Connection::open_in_memory().unwrap()).unwrap();
+        for (estimate, parent) in [(1, None), (2, Some(1)), (3, Some(2)), (4, None)] {
+            store
+                .create_task(Task {
+                    estimate,
+                    parent,
+                    ..Task::new("step")
+                })
+                .unwrap();
+        }
+        for task in [1, 3, 3, 4] {
+            store
+                .create_timer(pomodoro(datetime!(2024-05-01 09:00 +2), Some(task)))
+                .unwrap();
+        }
+
+        assert_eq!(store.get_task_estimate(1).unwrap(), 6);
+        assert_eq!(store.get_task_pomodoros(1).unwrap(), 3);
+        assert_eq!(store.get_task_estimate(2).unwrap(), 5);
+        assert_eq!(store.get_task_pomodoros(2).unwrap(), 2);
+        assert_eq!(store.get_task_pomodoros(4).unwrap(), 1);
+
+        store.create_project("work".to_string()).unwrap();
+        store.set_task_project(Some(1), 1).unwrap();
+        let moved: Vec<i32> = store
+            .get_tasks()
+            .unwrap()
+            .iter()
+            .filter(|task| task.project == Some(1))
+            .map(|task| task.id)
+            .collect();
+        assert_eq!(moved, [1, 2, 3]);
+    }
+
+    #[test]
+    fn done_tasks_are_filtered_by_completion_date() {
+        let mut store = SqliteStore::new(Connection::open_in_memory().unwrap()).unwrap();
+        for completed_at in [
+            Some(datetime!(2024-05-01 23:30 +2)),
+            Some(datetime!(2024-05-03 09:00 +2)),
+            None,
+        ] {
+            store
+                .create_task(Task {
+                    done: true,
+                    completed_at,
+                    ..Task::new("done")
+                })
+                .unwrap();
+        }
+        let ids = |tasks: Vec<Task>| -> Vec<i32> { tasks.iter().map(|task| task.id).collect() };
+
+        assert_eq!(ids(store.get_done_tasks(None, None).unwrap()), [2, 1, 3]);
+        assert_eq!(
+            ids(store
+                .get_done_tasks(Some(date!(2024 - 05 - 01)), Some(date!(2024 - 05 - 02)))
+                .unwrap()),
+            [1]
+        );
+
+        store.set_task_status(false, 2).unwrap();
+        assert_eq!(ids(store.get_done_tasks(None, None).unwrap()), [1, 3]);
+        assert_eq!(store.get_tasks().unwrap()[0].completed_at, None);
+    }
+
+    #[test]
+    fn ticking_off_a_repeating_task_adds_the_next_one() {
+        let mut store = SqliteStore::new(Connection::open_in_memory().unwrap()).unwrap();
+        let today = now().date();
+        store
+            .create_task(Task {
+                estimate: 1,
+                due: Some(today - Duration::days(2)),
+                recurrence: Some(Recurrence::Daily),
+                ..Task::new("standup")
+            })
+            .unwrap();
+        let mut timer = Timer::new(crate::model::TimerKind::Focus, 25, now());
+        timer.task = Some(1);
+        timer.status = TimerStatus::Completed;
+        store.create_timer(timer).unwrap();
+
+        store.set_task_status(true, 1).unwrap();
+        let next = &store.get_tasks().unwrap()[0];
+        assert_eq!(
+            (next.id, next.name.as_str(), next.estimate),
+            (2, "standup", 1)
+        );
+        assert_eq!(next.due, today.next_day());
+        assert_eq!(next.recurrence, Some(Recurrence::Daily));
+        assert_eq!(store.get_task_pomodoros(1).unwrap(), 1);
+        assert_eq!(store.get_task_pomodoros(2).unwrap(), 0);
+
+        // The rule moved on, so reopening and ticking off again doesn't repeat.
+        store.set_task_status(false, 1).unwrap();
+        store.set_task_status(true, 1).unwrap();
+        assert_eq!(store.get_all_tasks().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn interruptions_follow_their_timer() {
+        let mut store = SqliteStore::new(Connection::open_in_memory().unwrap()).unwrap();
+        store.create_task(Task::new("write report")).unwrap();
+        let start = datetime!(2024-05-01 09:00 +2);
+        for _ in 0..2 {
+            store.create_timer(pomodoro(start, None)).unwrap();
+        }
+        for (timer, kind, note) in [
+            (1, InterruptionKind::Internal, "check mail"),
+            (1, InterruptionKind::External, ""),
+            (2, InterruptionKind::External, "phone"),
+        ] {
+            store
+                .create_interruption(Interruption::new(
+                    timer,
+                    kind,
+                    start + Duration::minutes(5),
+                    note.to_string(),
+                ))
+                .unwrap();
+        }
+        assert_eq!(store.get_timer_interruptions(1).unwrap().len(), 2);
+
+        store.update_timer_task(1, 1).unwrap();
+        let timer = store.get_timer(1).unwrap().unwrap();
+        store.put_timer(&timer).unwrap();
+        // The second timer is deleted, its interruption goes with it.
+        store.delete_timer(2).unwrap();
+
+        let interruptions = store.get_task_interruptions(1).unwrap();
+        assert_eq!(crate::model::interruption_marks(&interruptions), "'-");
+        assert_eq!(interruptions[0].note, "check mail");
+        let statistics = store.get_statistics(date!(2024 - 01 - 01)).unwrap();
+        assert_eq!(
+            (
+                statistics.internal_interruptions,
+                statistics.external_interruptions
+            ),
+            (1, 1)
+        );
+    }
+    #[test]
+    fn voided_pomodoros_are_kept_but_not_counted() {
+        let mut store = SqliteStore::new(Connection::open_in_memory().unwrap()).unwrap();
+        let start = datetime!(2024-05-01 09:00 +2);
+        store.create_timer(pomodoro(start, None)).unwrap();
+        store
+            .set_timer_status(1, TimerStatus::Voided, "fire drill".to_string())
+            .unwrap();
+        assert!(store.get_running_timers().unwrap().is_empty());
+
+        let statistics = store.get_statistics(date!(2024 - 01 - 01)).unwrap();
+        assert!(statistics.days.is_empty());
+        assert_eq!(statistics.focus_minutes, 0);
+        let stopped = &statistics.stopped[0];
+        assert_eq!(
+            (stopped.status, stopped.reason.as_str()),
+            (TimerStatus::Voided, "fire drill")
+        );
+    }
+
+    #[test]
+    fn current_task_is_credited_only_once_the_pomodoro_completes() {
+        let mut store = SqliteStore::new(Connection::open_in_memory().unwrap()).unwrap();
+        store
+            .create_task(Task {
+                estimate: 1,
+                ..Task::new("write report")
+            })
+            .unwrap();
+        let start = datetime!(2024-05-01 09:00 +2);
+        store.create_timer(pomodoro(start, None)).unwrap();
+        store.set_timer_task(1, Some(1)).unwrap();
+
+        let running = store.get_running_timers().unwrap();
+        assert_eq!(running[0].task, Some(1));
+        assert_eq!(store.get_task_pomodoros(1).unwrap(), 0);
+
+        store.update_timer_task(1, 1).unwrap();
+        assert!(store.get_running_timers().unwrap().is_empty());
+        assert_eq!(store.get_task_pomodoros(1).unwrap(), 1);
+
+        // A task ticked off or deleted leaves the running timer it was on.
+        store.create_timer(pomodoro(start, None)).unwrap();
+        store.set_timer_task(2, Some(1)).unwrap();
+        store.set_task_status(true, 1).unwrap();
+        assert_eq!(store.get_running_timers().unwrap()[0].task, None);
+        store.create_task(Task::new("Other")).unwrap();
+        store.set_timer_task(2, Some(2)).unwrap();
+        store.delete_task(2).unwrap();
+        assert_eq!(store.get_running_timers().unwrap()[0].task, None);
+        // The credited pomodoro keeps its task.
+        assert_eq!(store.get_task_pomodoros(1).unwrap(), 1);
+    }
+
+    #[test]
+    fn breaks_keep_their_kind_whatever_their_duration() {
+        let mut store = SqliteStore::new(Connection::open_in_memory().unwrap()).unwrap();
+        let start = datetime!(2024-05-01 09:00 +2);
+        store
+            .create_timer(Timer::new(TimerKind::LongBreak, 5, start))
+            .unwrap();
+        assert_eq!(
+            store.get_running_timers().unwrap()[0].kind,
+            TimerKind::LongBreak
+        );
+
+        store
+            .save_queue(&[
+                Timer::new(TimerKind::ShortBreak, 30, start),
+                Timer::new(TimerKind::LongBreak, 5, start),
+            ])
+            .unwrap();
+        let kinds: Vec<TimerKind> = store
+            .get_queue()
+            .unwrap()
+            .iter()
+            .map(|timer| timer.kind)
+            .collect();
+        assert_eq!(kinds, [TimerKind::ShortBreak, TimerKind::LongBreak]);
+    }
+}
@@ -0,0 +1,580 @@
+//! Undo and redo for changes made through a `Store`.
+//!
+//! `History` wraps a store and compares tasks and running timers before and
+//! after each mutation; the differences make up an `Edit` that can be rolled
+//! back and forth. Projects, interruptions and the timer queue aren't
+//! covered, and the `locked`/`just_created` editing state of tasks is
+//! ignored. Starting, pausing and resuming a timer aren't edits either: the
+//! queue starts timers on its own, and undoing that would lose the queued
+//! timer. Undoing the deletion of a timer brings back its pauses but not its
+//! interruptions.
+use crate::error::Result;
+use crate::model::{now, Interruption, Project, Task, Timer, TimerStatus};
+use crate::recurrence::Recurrence;
+use crate::stats::{EstimateRecord, Statistics};
+use crate::store::Store;
+use std::collections::BTreeMap;
+use time::{Date, Duration, OffsetDateTime};
+
+enum Change {
+    Task {
+        before: Option<Task>,
+        after: Option<Task>,
+    },
+    Timer {
+        before: Option<Timer>,
+        after: Option<Timer>,
+    },
+}
+
+struct Edit {
+    label: String,
+    changes: Vec<Change>,
+}
+
+#[derive(PartialEq)]
+struct Snapshot {
+    tasks: BTreeMap<i32, Task>,
+    timers: BTreeMap<i32, Timer>,
+}
+
+fn diff<T: Clone + PartialEq>(
+    before: &BTreeMap<i32, T>,
+    after: &BTreeMap<i32, T>,
+) -> Vec<(Option<T>, Option<T>)> {
+    let mut changes = vec![];
+    for id in before
+        .keys()
+        .chain(after.keys().filter(|id| !before.contains_key(id)))
+    {
+        let (before, after) = (before.get(id), after.get(id));
+        if before != after {
+            changes.push((before.cloned(), after.cloned()));
+        }
+    }
+    changes
+}
+
+/// What an edit did, for the "Undo" toast.
+fn describe(changes: &[Change]) -> String {
+    let tasks = changes
+        .iter()
+        .filter(|change| matches!(change, Change::Task { .. }))
+        .count();
+    let plural = |what: &str| {
+        if tasks == 1 {
+            format!("Task {}", what)
+        } else {
+            format!("{} tasks {}", tasks, what)
+        }
+    };
+    match &changes[0] {
+        Change::Task {
+            before: None,
+            after: Some(_),
+        } => plural("added"),
+        Change::Task {
+            before: Some(_),
+            after: None,
+        } => plural("deleted"),
+        Change::Task {
+            before: Some(before),
+            after: Some(after),
+        } => {
+            if before.done != after.done {
+                plural(if after.done { "ticked off" } else { "reopened" })
+            } else if before.name != after.name {
+                plural("renamed")
+            } else if before.estimate != after.estimate {
+                "Estimate changed".to_string()
+            } else if before.due != after.due {
+                "Due date changed".to_string()
+            } else if before.planned != after.planned {
+                plural("planned")
+            } else if before.recurrence != after.recurrence {
+                "Repeat changed".to_string()
+            } else if before.priority != after.priority {
+                "Priority changed".to_string()
+            } else if before.tags != after.tags {
+                "Tags changed".to_string()
+            } else if before.notes != after.notes {
+                "Notes changed".to_string()
+            } else if before.project != after.project {
+                plural("moved")
+            } else if before.position != after.position {
+                "Tasks reordered".to_string()
+            } else {
+                plural("changed")
+            }
+        }
+        Change::Timer {
+            before: None,
+            after: Some(_),
+        } => "Timer started".to_string(),
+        Change::Timer {
+            before: Some(_),
+            after: None,
+        } => "Timer stopped".to_string(),
+        Change::Timer {
+            before: Some(before),
+            after: Some(after),
+        } => {
+            if before.status != after.status {
+                match (after.is_pomodoro(), after.status) {
+                    (true, TimerStatus::Completed) => "Pomodoro credited",
+                    (true, TimerStatus::Voided) => "Pomodoro voided",
+                    (true, TimerStatus::Abandoned) => "Pomodoro abandoned",
+                    (false, TimerStatus::Voided) => "Break cut short",
+                    (false, TimerStatus::Completed) => "Break finished",
+                    _ => "Timer changed",
+                }
+                .to_string()
+            } else if after.task.is_some() {
+                "Current task set".to_string()
+            } else {
+                "Current task cleared".to_string()
+            }
+        }
+        Change::Task {
+            before: None,
+            after: None,
+        }
+        | Change::Timer {
+            before: None,
+            after: None,
+        } => "Nothing changed".to_string(),
+    }
+}
+
+pub struct History {
+    store: Box<dyn Store>,
+    undo: Vec<Edit>,
+    redo: Vec<Edit>,
+    /// Bumped for every new edit, so the UI can tell when to offer "Undo".
+    generation: u64,
+}
+
+impl History {
+    pub fn new(store: Box<dyn Store>) -> Self {
+        History {
+            store,
+            undo: vec![],
+            redo: vec![],
+            generation: 0,
+        }
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// What `undo` would revert.
+    pub fn undo_label(&self) -> Option<&str> {
+        self.undo.last().map(|edit| edit.label.as_str())
+    }
+
+    /// Tasks as the user sees them, plus the running timers and `timer_ids`.
+    /// Tasks without a name are still being typed and don't count yet.
+    fn snapshot(&self, timer_ids: &[i32]) -> Result<Snapshot> {
+        let mut tasks = BTreeMap::new();
+        for mut task in self.store.get_all_tasks()? {
+            if !task.name.is_empty() {
+                task.locked = true;
+                task.just_created = false;
+                tasks.insert(task.id, task);
+            }
+        }
+        let mut timers = BTreeMap::new();
+        for timer in self.store.get_running_timers()? {
+            timers.insert(timer.id, timer);
+        }
+        for id in timer_ids {
+            if let Some(timer) = self.store.get_timer(*id)? {
+                timers.insert(timer.id, timer);
+            }
+        }
+        Ok(Snapshot { tasks, timers })
+    }
+
+    /// Runs `change` against the store as a single edit. `timer_ids` names
+    /// timers it touches that aren't running before or after it.
+    pub fn batch<T>(
+        &mut self,
+        timer_ids: &[i32],
+        change: impl FnOnce(&mut dyn Store) -> Result<T>,
+    ) -> Result<T> {
+        let before = self.snapshot(timer_ids)?;
+        let result = change(self.store.as_mut())?;
+        let after = self.snapshot(timer_ids)?;
+        if before != after {
+            let mut changes: Vec<Change> = diff(&before.tasks, &after.tasks)
+                .into_iter()
+                .map(|(before, after)| Change::Task { before, after })
+                .collect();
+            changes.extend(
+                diff(&before.timers, &after.timers)
+                    .into_iter()
+                    .map(|(before, after)| Change::Timer { before, after }),
+            );
+            self.undo.push(Edit {
+                label: describe(&changes),
+                changes,
+            });
+            self.redo.clear();
+            self.generation += 1;
+        }
+        Ok(result)
+    }
+
+    fn apply(&mut self, edit: &Edit, undo: bool) -> Result<()> {
+        let changes: Vec<&Change> = if undo {
+            edit.changes.iter().rev().collect()
+        } else {
+            edit.changes.iter().collect()
+        };
+        for change in changes {
+            match change {
+                Change::Task { before, after } => {
+                    let (from, to) = if undo {
+                        (after, before)
+                    } else {
+                        (before, after)
+                    };
+                    match (from, to) {
+                        (_, Some(task)) => self.store.put_task(task)?,
+                        (Some(task), None) => self.store.delete_task(task.id)?,
+                        (None, None) => {}
+                    }
+                }
+                Change::Timer { before, after } => {
+                    let (from, to) = if undo {
+                        (after, before)
+                    } else {
+                        (before, after)
+                    };
+                    match (from, to) {
+                        (_, Some(timer)) => self.store.put_timer(timer)?,
+                        (Some(timer), None) => self.store.delete_timer(timer.id)?,
+                        (None, None) => {}
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Reverts the last edit. Returns what was undone, if anything.
+    pub fn undo(&mut self) -> Result<Option<String>> {
+        let Some(edit) = self.undo.pop() else {
+            return Ok(None);
+        };
+        self.apply(&edit, true)?;
+        let label = edit.label.clone();
+        self.redo.push(edit);
+        Ok(Some(label))
+    }
+
+    /// Reapplies the last undone edit. Returns what was redone, if anything.
+    pub fn redo(&mut self) -> Result<Option<String>> {
+        let Some(edit) = self.redo.pop() else {
+            return Ok(None);
+        };
+        self.apply(&edit, false)?;
+        let label = edit.label.clone();
+        self.undo.push(edit);
+        Ok(Some(label))
+    }
+}
+
+impl Store for History {
+    fn get_tasks(&self) -> Result<Vec<Task>> {
+        self.store.get_tasks()
+    }
+
+    fn get_done_tasks(&self, from: Option<Date>, to: Option<Date>) -> Result<Vec<Task>> {
+        self.store.get_done_tasks(from, to)
+    }
+
+    fn get_all_tasks(&self) -> Result<Vec<Task>> {
+        self.store.get_all_tasks()
+    }
+
+    fn create_task(&mut self, task: Task) -> Result<i32> {
+        self.batch(&[], |store| store.create_task(task))
+    }
+
+    fn set_task_status(&mut self, done: bool, id: i32) -> Result<()> {
+        self.batch(&[], |store| store.set_task_status(done, id))
+    }
+
+    fn set_task_locked(&mut self, locked: bool, id: i32) -> Result<()> {
+        self.store.set_task_locked(locked, id)
+    }
+
+    fn set_task_just_created(&mut self, just_created: bool, id: i32) -> Result<()> {
+        self.store.set_task_just_created(just_created, id)
+    }
+
+    fn set_task_name(&mut self, name: String, id: i32) -> Result<()> {
+        self.batch(&[], |store| store.set_task_name(name, id))
+    }
+
+    fn set_task_estimate(&mut self, estimate: i32, id: i32) -> Result<()> {
+        self.batch(&[], |store| store.set_task_estimate(estimate, id))
+    }
+
+    fn set_task_due(&mut self, due: Option<Date>, id: i32) -> Result<()> {
+        self.batch(&[], |store| store.set_task_due(due, id))
+    }
+
+    fn set_task_planned(&mut self, planned: Option<Date>, id: i32) -> Result<()> {
+        self.batch(&[], |store| store.set_task_planned(planned, id))
+    }
+
+    fn set_task_recurrence(&mut self, recurrence: Option<Recurrence>, id: i32) -> Result<()> {
+        self.batch(&[], |store| store.set_task_recurrence(recurrence, id))
+    }
+
+    fn set_task_tags(&mut self, tags: Vec<String>, id: i32) -> Result<()> {
+        self.batch(&[], |store| store.set_task_tags(tags, id))
+    }
+
+    fn set_task_priority(&mut self, priority: u8, id: i32) -> Result<()> {
+        self.batch(&[], |store| store.set_task_priority(priority, id))
+    }
+
+    fn set_task_notes(&mut self, notes: String, id: i32) -> Result<()> {
+        self.batch(&[], |store| store.set_task_notes(notes, id))
+    }
+
+    fn delete_task(&mut self, id: i32) -> Result<()> {
+        self.batch(&[], |store| store.delete_task(id))
+    }
+
+    fn put_task(&mut self, task: &Task) -> Result<()> {
+        self.batch(&[], |store| store.put_task(task))
+    }
+
+    fn set_task_order(&mut self, ids: &[i32]) -> Result<()> {
+        self.batch(&[], |store| store.set_task_order(ids))
+    }
+
+    fn set_task_project(&mut self, project: Option<i32>, id: i32) -> Result<()> {
+        self.batch(&[], |store| store.set_task_project(project, id))
+    }
+
+    fn get_projects(&self) -> Result<Vec<Project>> {
+        self.store.get_projects()
+    }
+
+    fn create_project(&mut self, name: String) -> Result<()> {
+        self.store.create_project(name)
+    }
+
+    fn get_project_pomodoros(&self, project: Option<i32>) -> Result<usize> {
+        self.store.get_project_pomodoros(project)
+    }
+
+    fn get_running_timers(&self) -> Result<Vec<Timer>> {
+        self.store.get_running_timers()
+    }
+
+    fn get_all_timers(&self) -> Result<Vec<Timer>> {
+        self.store.get_all_timers()
+    }
+
+    fn get_timer(&self, id: i32) -> Result<Option<Timer>> {
+        self.store.get_timer(id)
+    }
+
+    fn create_timer(&mut self, timer: Timer) -> Result<()> {
+        self.store.create_timer(timer)
+    }
+
+    fn put_timer(&mut self, timer: &Timer) -> Result<()> {
+        self.batch(&[timer.id], |store| store.put_timer(timer))
+    }
+
+    fn delete_timer(&mut self, id: i32) -> Result<()> {
+        self.batch(&[id], |store| store.delete_timer(id))
+    }
+
+    fn pause_timer(&mut self, timer_id: i32, at: OffsetDateTime) -> Result<()> {
+        self.store.pause_timer(timer_id, at)
+    }
+
+    fn resume_timer(&mut self, timer_id: i32, at: OffsetDateTime) -> Result<()> {
+        self.store.resume_timer(timer_id, at)
+    }
+
+    /// Undoing a credit doesn't bring back a pomodoro that is over with its
+    /// task, or it would be credited again on the spot: it's left abandoned.
+    fn update_timer_task(&mut self, timer_id: i32, task_id: i32) -> Result<()> {
+        let generation = self.generation;
+        self.batch(&[timer_id], |store| {
+            store.update_timer_task(timer_id, task_id)
+        })?;
+        if self.generation == generation {
+            return Ok(());
+        }
+        let now = now();
+        for change in &mut self.undo.last_mut().unwrap().changes {
+            if let Change::Timer {
+                before: Some(timer),
+                ..
+            } = change
+            {
+                if timer.id == timer_id
+                    && timer.status == TimerStatus::Running
+                    && timer.remaining(now) <= Duration::ZERO
+                {
+                    timer.status = TimerStatus::Abandoned;
+                    timer.task = None;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn set_timer_task(&mut self, timer_id: i32, task_id: Option<i32>) -> Result<()> {
+        self.batch(&[timer_id], |store| store.set_timer_task(timer_id, task_id))
+    }
+
+    fn set_timer_status(
+        &mut self,
+        timer_id: i32,
+        status: TimerStatus,
+        reason: String,
+    ) -> Result<()> {
+        self.batch(&[timer_id], |store| {
+            store.set_timer_status(timer_id, status, reason)
+        })
+    }
+
+    fn get_timer_interruptions(&self, timer_id: i32) -> Result<Vec<Interruption>> {
+        self.store.get_timer_interruptions(timer_id)
+    }
+
+    fn get_task_interruptions(&self, task_id: i32) -> Result<Vec<Interruption>> {
+        self.store.get_task_interruptions(task_id)
+    }
+
+    fn create_interruption(&mut self, interruption: Interruption) -> Result<()> {
+        self.store.create_interruption(interruption)
+    }
+
+    fn get_queue(&self) -> Result<Vec<Timer>> {
+        self.store.get_queue()
+    }
+
+    fn save_queue(&mut self, queue: &[Timer]) -> Result<()> {
+        self.store.save_queue(queue)
+    }
+
+    fn get_task_pomodoros(&self, task_id: i32) -> Result<usize> {
+        self.store.get_task_pomodoros(task_id)
+    }
+
+    fn get_task_estimate(&self, task_id: i32) -> Result<i32> {
+        self.store.get_task_estimate(task_id)
+    }
+
+    fn get_pomodoros_median(&self) -> Result<i32> {
+        self.store.get_pomodoros_median()
+    }
+
+    fn get_statistics(&self, since: Date) -> Result<Statistics> {
+        self.store.get_statistics(since)
+    }
+
+    fn get_estimate_history(&self) -> Result<Vec<EstimateRecord>> {
+        self.store.get_estimate_history()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::TimerKind;
+    use crate::store::InMemoryStore;
+
+    fn history_with_task() -> History {
+        let mut history = History::new(Box::new(InMemoryStore::new()));
+        history
+            .create_task(Task {
+                estimate: 2,
+                ..Task::new("write report")
+            })
+            .unwrap();
+        history
+    }
+
+    #[test]
+    fn undo_and_redo_task_changes() {
+        let mut history = history_with_task();
+        history.set_task_status(true, 1).unwrap();
+        assert_eq!(history.undo_label(), Some("Task ticked off"));
+        assert!(history.get_tasks().unwrap().is_empty());
+
+        assert_eq!(history.undo().unwrap().as_deref(), Some("Task ticked off"));
+        let task = &history.get_tasks().unwrap()[0];
+        assert!(!task.done);
+        assert_eq!(task.completed_at, None);
+
+        history.redo().unwrap();
+        assert!(history.get_tasks().unwrap().is_empty());
+        assert_eq!(history.redo().unwrap(), None);
+    }
+
+    #[test]
+    fn deleted_task_comes_back_with_its_id() {
+        let mut history = history_with_task();
+        history.set_task_name("".to_string(), 1).unwrap();
+        history.delete_task(1).unwrap();
+        assert_eq!(history.undo_label(), Some("Task deleted"));
+
+        history.undo().unwrap();
+        let tasks = history.get_tasks().unwrap();
+        assert_eq!(tasks[0].id, 1);
+        assert_eq!(tasks[0].name, "write report");
+    }
+
+    #[test]
+    fn stopped_timer_is_restored_with_its_pause() {
+        let mut history = history_with_task();
+        let generation = history.generation();
+        let start = now();
+        history
+            .create_timer(Timer::new(TimerKind::Focus, 25, start))
+            .unwrap();
+        let id = history.get_running_timers().unwrap()[0].id;
+        history
+            .pause_timer(id, start + time::Duration::minutes(5))
+            .unwrap();
+        history
+            .resume_timer(id, start + time::Duration::minutes(7))
+            .unwrap();
+        // Starting and pausing the timer aren't edits of their own.
+        assert_eq!(history.generation(), generation);
+        history.delete_timer(id).unwrap();
+        assert_eq!(history.undo_label(), Some("Timer stopped"));
+
+        history.undo().unwrap();
+        let timer = &history.get_running_timers().unwrap()[0];
+        assert_eq!(timer.paused, time::Duration::minutes(2));
+
+        history.update_timer_task(id, 1).unwrap();
+        assert_eq!(history.undo_label(), Some("Pomodoro credited"));
+        history.undo().unwrap();
+        assert_eq!(history.get_task_pomodoros(1).unwrap(), 0);
+        assert_eq!(history.get_running_timers().unwrap().len(), 1);
+
+        history
+            .set_timer_status(id, TimerStatus::Voided, String::new())
+            .unwrap();
+        assert_eq!(history.undo_label(), Some("Pomodoro voided"));
+        assert!(history.get_running_timers().unwrap().is_empty());
+        history.undo().unwrap();
+        assert_eq!(history.get_running_timers().unwrap().len(), 1);
+    }
+}